@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+
+use crate::palette::{contrast_ratio, oklab_distance, Palette, Rgb, TermBackground};
+use crate::view::is_extension;
+use crate::{capability, categories, Color, ColorMap, LsItem, Style};
+
+/// how an entry looks on the terminal
+#[derive(Debug, Clone)]
+pub struct Look {
+    pub key: String,
+    /// category of an extension, the key of a file type
+    pub class: String,
+    pub fg: Rgb,
+    pub bg: Rgb,
+    /// bold, underscore and reverse are noticeable on their own
+    pub attributes: (bool, bool, bool),
+    pub values: String,
+}
+
+#[derive(Debug)]
+pub struct Cluster {
+    pub looks: Vec<Look>,
+    /// largest distance between two members
+    pub spread: f64,
+    pub suggestion: Option<String>,
+}
+
+fn look(key: &str, item: &LsItem, class: String, palette: &Palette, terminal: (Rgb, Rgb)) -> Look {
+    let (fg, bg) = palette.effective_colors(item, terminal);
+    let has = |style| item.style == Some(style);
+    Look { key: key.to_string(), class, fg, bg, attributes: (has(Style::Bold), has(Style::Underscore), has(Style::Reverse)), values: item.to_string() }
+}
+
+/// perceptual distance of two looks, infinite when the attributes differ
+pub fn distance(a: &Look, b: &Look) -> f64 {
+    if a.attributes != b.attributes {
+        return f64::INFINITY;
+    }
+    oklab_distance(a.fg, b.fg).max(oklab_distance(a.bg, b.bg))
+}
+
+/// the colored entries with their file class: the category of an extension, 'other extensions' or the key itself
+pub fn looks(map: &ColorMap, categories: &BTreeMap<String, Vec<String>>, palette: &Palette, background: TermBackground) -> Vec<Look> {
+    let flat = map.flatten(false);
+    let terminal = palette.terminal_colors(background);
+    let mut entries: Vec<(&String, &LsItem)> = flat.iter().filter(|(_, item)| item.color.is_some() || item.bg.is_some()).collect();
+    entries.sort_by_key(|(_, item)| item.order);
+    entries.into_iter().map(|(key, item)| {
+        let class = if is_extension(key) {
+            categories::category_of(categories, key).unwrap_or("other extensions").to_string()
+        } else {
+            key.clone()
+        };
+        look(key, item, class, palette, terminal)
+    }).collect()
+}
+
+/// groups of entries chained by distances below `threshold` that mix several file classes
+pub fn clusters(looks: &[Look], threshold: f64, palette: &Palette, background: TermBackground) -> Vec<Cluster> {
+    // union find over the pairs that are too close
+    let mut parent: Vec<usize> = (0..looks.len()).collect();
+    fn root(parent: &mut [usize], i: usize) -> usize {
+        let mut i = i;
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..looks.len() {
+        for j in i + 1..looks.len() {
+            if distance(&looks[i], &looks[j]) < threshold {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[b] = a;
+            }
+        }
+    }
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..looks.len() {
+        groups.entry(root(&mut parent, i)).or_default().push(i);
+    }
+
+    let mut clusters = vec![];
+    for members in groups.into_values() {
+        let mut classes: Vec<&str> = members.iter().map(|i| looks[*i].class.as_str()).collect();
+        classes.sort();
+        classes.dedup();
+        if classes.len() < 2 {
+            continue;
+        }
+        let cluster_looks: Vec<Look> = members.iter().map(|i| looks[*i].clone()).collect();
+        let spread = cluster_looks.iter().flat_map(|a| cluster_looks.iter().map(move |b| distance(a, b))).fold(0.0, f64::max);
+        let suggestion = suggest(looks, &cluster_looks, palette, background);
+        clusters.push(Cluster { looks: cluster_looks, spread, suggestion });
+    }
+    clusters
+}
+
+/// moves the class with the fewest entries in the cluster to the basic color farthest from every other look
+fn suggest(all: &[Look], cluster: &[Look], palette: &Palette, background: TermBackground) -> Option<String> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for l in cluster {
+        *counts.entry(l.class.as_str()).or_default() += 1;
+    }
+    let (class, _) = counts.into_iter().min_by_key(|(_, count)| *count)?;
+    let moved: Vec<&Look> = cluster.iter().filter(|l| l.class == class).collect();
+    let others: Vec<&Look> = all.iter().filter(|l| l.class != class).collect();
+    let (terminal_fg, terminal_bg) = palette.terminal_colors(background);
+
+    let candidates = (1..16u8).map(Color::from_index).filter(|c| contrast_ratio(palette.rgb(c), terminal_bg) >= 3.0);
+    let (color, gap) = candidates.map(|color| {
+        let fg = palette.rgb(&color);
+        // uncolored files use the text color of the terminal
+        let plain = oklab_distance(fg, terminal_fg);
+        let gap = others.iter().map(|o| distance(&Look { fg, ..moved[0].clone() }, o)).fold(plain, f64::min);
+        (color, gap)
+    }).max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    let target = if moved[0].key == class {
+        class.to_string()
+    } else if class == "other extensions" {
+        moved[0].key.clone()
+    } else {
+        format!("category:{}", class)
+    };
+    Some(format!("set {} color {} (the closest other entry is then {:.2} away)", target, color, gap))
+}
+
+pub fn print_clusters(clusters: &[Cluster], threshold: f64, background: TermBackground) {
+    if clusters.is_empty() {
+        println!("{} background: no file classes look alike (threshold {})", background.name(), threshold);
+        return;
+    }
+    println!("{} background: {} groups of file classes look alike (threshold {})", background.name(), clusters.len(), threshold);
+    for (i, cluster) in clusters.iter().enumerate() {
+        let classes: Vec<&str> = cluster.looks.iter().map(|l| l.class.as_str()).fold(vec![], |mut acc, c| {
+            if !acc.contains(&c) {
+                acc.push(c);
+            }
+            acc
+        });
+        println!("{}. {} (distance up to {:.3})", i + 1, classes.join(", "), cluster.spread);
+        for l in &cluster.looks {
+            let sample = capability::paint(&l.values, &format!("{:<12}", l.key));
+            println!("     {} {:<16} {}", sample, l.values, l.class);
+        }
+        if let Some(suggestion) = &cluster.suggestion {
+            println!("   try: {}", suggestion);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::{env, fs, io::{self, IsTerminal}, path::PathBuf, sync::OnceLock};
+
+use clap::ValueEnum;
+
+use crate::depth::Depth;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorChoice {
+    /// colors when the output is a terminal that supports them and NO_COLOR is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// depth of the output, None for plain text; decided once
+static OUTPUT: OnceLock<Option<Depth>> = OnceLock::new();
+
+/// decides how the preview is written, call before printing anything
+pub fn init(choice: ColorChoice) {
+    let _ = OUTPUT.set(match choice {
+        ColorChoice::Auto => detect(),
+        ColorChoice::Always => Some(terminal_depth().unwrap_or(Depth::Ansi16)),
+        ColorChoice::Never => None,
+    });
+}
+
+/// color depth of the output (the preview, not `export`), None when colors are off
+pub fn depth() -> Option<Depth> {
+    *OUTPUT.get_or_init(detect)
+}
+
+pub fn colors_enabled() -> bool {
+    depth().is_some()
+}
+
+/// the text with the SGR codes, or the bare text when colors are off
+pub fn paint(codes: &str, text: &str) -> String {
+    if colors_enabled() {
+        format!("\x1b[{}m{}\x1b[0m", codes, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// no colors with NO_COLOR or when stdout is not a terminal, otherwise what the terminal supports
+pub fn detect() -> Option<Depth> {
+    if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) || !io::stdout().is_terminal() {
+        return None;
+    }
+    terminal_depth()
+}
+
+/// the depth from COLORTERM, terminfo and the name in TERM
+pub fn terminal_depth() -> Option<Depth> {
+    let term = env::var("TERM").unwrap_or_default();
+    if term.is_empty() || term == "dumb" {
+        return None;
+    }
+    if matches!(env::var("COLORTERM").as_deref(), Ok("truecolor") | Ok("24bit")) {
+        return Some(Depth::Truecolor);
+    }
+    match terminfo_colors(&term) {
+        Some(n) if n >= 1 << 24 => Some(Depth::Truecolor),
+        Some(n) if n >= 256 => Some(Depth::Ansi256),
+        Some(n) if n >= 8 => Some(Depth::Ansi16),
+        Some(_) => None,
+        // no terminfo entry, guess from the name
+        None if term.contains("256col") => Some(Depth::Ansi256),
+        None => Some(Depth::Ansi16),
+    }
+}
+
+/// the directories searched for compiled terminfo entries, in the order ncurses uses
+fn terminfo_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    if let Some(dir) = env::var_os("TERMINFO") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".terminfo"));
+    }
+    if let Ok(list) = env::var("TERMINFO_DIRS") {
+        dirs.extend(list.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+    }
+    dirs.extend(["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo", "/usr/lib/terminfo"].map(PathBuf::from));
+    dirs
+}
+
+/// the 'colors' capability of the terminfo entry, None when there is no entry
+pub fn terminfo_colors(term: &str) -> Option<i32> {
+    let first = term.chars().next()?;
+    let data = terminfo_dirs().into_iter().find_map(|dir| {
+        // 'x/xterm' on Linux, '78/xterm' on macOS
+        fs::read(dir.join(first.to_string()).join(term)).or_else(|_| fs::read(dir.join(format!("{:x}", first as u32)).join(term))).ok()
+    })?;
+    parse_colors(&data)
+}
+
+/// reads the number of colors from a compiled terminfo entry (legacy or 32 bit number format)
+fn parse_colors(data: &[u8]) -> Option<i32> {
+    // index of 'colors' in the numbers section
+    const COLORS: usize = 13;
+    let short = |i: usize| data.get(2 * i..2 * i + 2).map(|b| i16::from_le_bytes([b[0], b[1]]));
+    let width = match short(0)? {
+        0o432 => 2,
+        0o1036 => 4,
+        _ => return None,
+    };
+    let (names, bools, numbers) = (short(1)? as usize, short(2)? as usize, short(3)? as usize);
+    if numbers <= COLORS {
+        return Some(-1);
+    }
+    // the numbers start on an even byte
+    let start = (12 + names + bools).next_multiple_of(2) + COLORS * width;
+    let bytes = data.get(start..start + width)?;
+    Some(match width {
+        2 => i16::from_le_bytes([bytes[0], bytes[1]]) as i32,
+        _ => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    })
+}
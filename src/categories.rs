@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+
+use crate::theme::ThemeInfo;
+
+/// built-in extension catalog, the theme can override each category
+pub const CATALOG: &[(&str, &[&str])] = &[
+    ("archive", &["tar", "tgz", "taz", "zip", "gz", "bz2", "xz", "zst", "7z", "rar", "lz", "lzma", "lz4", "tbz2", "txz", "cpio", "jar", "deb", "rpm"]),
+    ("image", &["jpg", "jpeg", "png", "gif", "bmp", "tif", "tiff", "svg", "webp", "ico", "avif", "heic", "xpm", "pcx"]),
+    ("audio", &["mp3", "flac", "ogg", "oga", "wav", "m4a", "aac", "opus", "wma", "mid", "midi"]),
+    ("video", &["mp4", "mkv", "avi", "mov", "webm", "wmv", "flv", "mpg", "mpeg", "m4v", "ogv"]),
+    ("document", &["pdf", "doc", "docx", "odt", "xls", "xlsx", "ods", "ppt", "pptx", "odp", "txt", "md", "rtf", "epub"]),
+    ("source", &["c", "h", "cpp", "hpp", "rs", "py", "js", "ts", "go", "java", "rb", "sh", "pl", "lua"]),
+    ("backup", &["bak", "old", "orig", "swp", "swo", "tmp"]),
+];
+
+fn catalog_members(name: &str) -> Option<Vec<String>> {
+    CATALOG.iter().find(|(n, _)| *n == name).map(|(_, exts)| exts.iter().map(|e| format!("*.{}", e)).collect())
+}
+
+/// the catalog with the categories of the theme applied on top
+pub fn all(info: &ThemeInfo) -> BTreeMap<String, Vec<String>> {
+    let mut categories: BTreeMap<String, Vec<String>> = CATALOG.iter().map(|(name, _)| (name.to_string(), catalog_members(name).unwrap())).collect();
+    for (name, members) in &info.categories {
+        categories.insert(name.clone(), members.clone());
+    }
+    categories
+}
+
+pub fn members(info: &ThemeInfo, name: &str) -> Option<Vec<String>> {
+    info.categories.get(name).cloned().or_else(|| catalog_members(name))
+}
+
+/// name of the first category containing the key
+pub fn category_of<'a>(categories: &'a BTreeMap<String, Vec<String>>, key: &str) -> Option<&'a str> {
+    categories.iter().find(|(_, members)| members.iter().any(|m| m == key)).map(|(name, _)| name.as_str())
+}
+
+/// adds extensions to a category, unknown categories are created
+pub fn add_members(info: &mut ThemeInfo, name: &str, keys: &[String]) -> Result<(), String> {
+    if let Some(key) = keys.iter().find(|k| !k.starts_with("*.")) {
+        return Err(format!("only file extensions (*.ext) can be part of a category: {}", key));
+    }
+    let mut current = members(info, name).unwrap_or_default();
+    for key in keys {
+        if !current.contains(key) {
+            current.push(key.clone());
+        }
+    }
+    info.categories.insert(name.to_string(), current);
+    Ok(())
+}
+
+pub fn remove_members(info: &mut ThemeInfo, name: &str, keys: &[String]) -> Result<(), String> {
+    let mut current = members(info, name).ok_or(format!("unknown category: {}", name))?;
+    current.retain(|m| !keys.contains(m));
+    info.categories.insert(name.to_string(), current);
+    Ok(())
+}
@@ -1,7 +1,241 @@
 
-use std::str::FromStr;
+use std::{collections::HashSet, fs, path::{Path, PathBuf}, str::FromStr, time::Duration};
 
-use crate::{Background, Color, ColorMap, LsItem, Style};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::{create_ls_string, default_description, is_variable, json, Background, Color, ColorMap, LsItem, Style};
+use crate::{analyze, categories, compact, depth, dircolors, diff, eza, lf, lscolors, lsd, merge, theme, vivid};
+use crate::depth::Depth;
+use crate::layers::{self, Layer, LayerStack};
+use crate::lint::LintOptions;
+use crate::palette::{self, Palette, TermBackground};
+use crate::schemes::{self, SchemeFormat};
+use crate::query;
+use crate::schema::{self, Schema};
+use crate::theme::ThemeInfo;
+use crate::view::ViewArgs;
+
+/// the commands understood by the prompt
+#[derive(Parser, Debug)]
+#[command(multicall = true, disable_help_subcommand = true)]
+pub struct Repl {
+    #[command(subcommand)]
+    pub command: ReplCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ReplCommand {
+    /// show preview
+    View(ViewArgs),
+    /// show help
+    Help,
+    /// prints the result
+    Export(ExportArgs),
+    /// replaces the current map with the content of a file
+    Import {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Ls)]
+        format: Format,
+        /// vivid's filetypes database, needed with --format vivid
+        #[arg(long)]
+        filetypes: Option<PathBuf>,
+    },
+    /// back to your prompt
+    Quit,
+    /// writes the current map with its metadata as a toml theme
+    Save { file: PathBuf },
+    /// replaces the current map with a toml theme
+    Load { file: PathBuf },
+    /// shows or sets the theme metadata
+    Theme {
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        author: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// lists and edits the extension categories
+    Category {
+        #[command(subcommand)]
+        action: CategoryAction,
+    },
+    /// compares two configurations (default: the start of the session with the current state)
+    Diff {
+        /// current, initial, env, defaults or a file
+        a: Option<String>,
+        b: Option<String>,
+        /// only show the customizations of A (default: current) relative to the GNU defaults
+        #[arg(long, conflicts_with = "b")]
+        against_defaults: bool,
+    },
+    /// three-way merge of themes, the result replaces the current map unless written to a file
+    Merge {
+        /// current, initial, env, defaults or a file
+        base: String,
+        ours: String,
+        theirs: String,
+        /// asks which side to take for every conflict
+        #[arg(long, short)]
+        interactive: bool,
+        /// writes the result with conflict markers instead
+        #[arg(long, short, conflicts_with = "interactive")]
+        output: Option<PathBuf>,
+        /// format of the output file
+        #[arg(long, value_enum, default_value_t = Format::Dircolors)]
+        format: Format,
+    },
+    /// checks the entries for problems
+    Lint(LintArgs),
+    /// finds file classes that look nearly identical
+    Analyze {
+        /// OKLab distance below which two entries look alike
+        #[arg(long, default_value_t = 0.05)]
+        threshold: f64,
+        #[arg(long, value_enum, default_value_t = TermBackground::Dark)]
+        background: TermBackground,
+    },
+    /// shows or switches the variable being edited (LS_COLORS, GREP_COLORS, GCC_COLORS, TREE_COLORS, LF_COLORS)
+    Variable { name: Option<String> },
+    /// shows the layer stack and where each entry comes from
+    Layers {
+        #[command(subcommand)]
+        action: Option<LayerAction>,
+    },
+    /// shows or changes the terminal palette used for previews and contrast checks
+    Palette {
+        #[command(subcommand)]
+        action: Option<PaletteAction>,
+    },
+    /// attaches a note to an entry (no text removes the note)
+    Note { which: String, text: Vec<String> },
+    /// updates an entry
+    Set { which: String, what: String, value: String },
+    /// adds a file extension
+    Add { which: String, what: String, value: String },
+    /// removes a file extension or unsets an entry
+    Remove { which: String },
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    #[arg(long, value_enum, default_value_t = Format::Ls)]
+    pub format: Format,
+    /// write to a file instead of printing
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+    /// only the entries that differ from the layers up to LAYER (default: base)
+    #[arg(long, value_name = "LAYER", num_args = 0..=1, default_missing_value = "base")]
+    pub delta: Option<String>,
+    /// drop the entries ls doesn't need and the redundant attributes
+    #[arg(long)]
+    pub minify: bool,
+    /// deterministic order: file types first, then the patterns sorted by key
+    #[arg(long)]
+    pub canonical: bool,
+    /// replace the colors by the nearest ones a terminal with this depth can show
+    #[arg(long, value_enum)]
+    pub depth: Option<Depth>,
+    /// dircolors sections gated by TERM/COLORTERM, each downsampled for its terminal class
+    #[arg(long, conflicts_with = "depth")]
+    pub per_term: bool,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct LintArgs {
+    /// maximum length of the exported LS_COLORS string
+    #[arg(long, default_value_t = 4096)]
+    pub max_length: usize,
+    /// minimum contrast ratio between text and background (WCAG: 4.5)
+    #[arg(long, default_value_t = 4.5)]
+    pub min_contrast: f64,
+    /// terminal background the contrast is checked against
+    #[arg(long, value_enum, default_value_t = TermBackground::Both)]
+    pub background: TermBackground,
+    /// also report the entries the program colors on its own
+    #[arg(long)]
+    pub include_defaults: bool,
+}
+
+impl LintArgs {
+    /// `defaults`: the keys the user never set, see LayerStack::default_keys
+    pub fn options(&self, palette: &Palette, defaults: HashSet<String>) -> LintOptions {
+        let defaults = if self.include_defaults { HashSet::new() } else { defaults };
+        LintOptions { max_length: self.max_length, min_contrast: self.min_contrast, background: self.background, palette: palette.clone(), defaults }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CategoryAction {
+    /// shows all categories with their members
+    List,
+    /// adds extensions to a category (unknown categories are created)
+    Add { name: String, keys: Vec<String> },
+    /// removes extensions from a category
+    Remove { name: String, keys: Vec<String> },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LayerAction {
+    /// adds a file (toml, json, LS_COLORS string or dircolors) as layer
+    Add {
+        file: PathBuf,
+        /// defaults to the file name
+        #[arg(long)]
+        name: Option<String>,
+        /// position in the stack, defaults to below the env layer
+        #[arg(long)]
+        position: Option<usize>,
+    },
+    /// removes a layer from the stack
+    Remove { name: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PaletteAction {
+    /// replaces the palette with a preset (xterm, vga, tango)
+    Use { name: String },
+    /// sets a color (0-15 or name), fg or bg, ex.: 'palette set blue 3465a4'
+    Set { slot: String, value: String },
+    /// reads the color scheme of a terminal emulator
+    Import {
+        file: PathBuf,
+        /// guessed from the file name and content when missing
+        #[arg(long, value_enum)]
+        format: Option<SchemeFormat>,
+    },
+    /// asks the running terminal for its colors (OSC 4/10/11), the answer is cached per terminal
+    Query {
+        /// ask again instead of using the cache
+        #[arg(long)]
+        refresh: bool,
+        /// how long to wait for the answer in milliseconds
+        #[arg(long, default_value_t = 500)]
+        timeout: u64,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// LS_COLORS string
+    Ls,
+    /// json array of all entries
+    Json,
+    /// dircolors database
+    Dircolors,
+    /// toml theme
+    Toml,
+    /// BSD/macOS LSCOLORS (11 file types, 8 colors)
+    Lscolors,
+    /// EZA_COLORS string (file types, patterns and the eza keys)
+    Eza,
+    /// eza theme.yml
+    EzaTheme,
+    /// file-type section of lsd's colors.yaml
+    Lsd,
+    /// vivid theme (import only, with --filetypes)
+    Vivid,
+}
 
 
 //mpl std::error::Error for String {}
@@ -9,15 +243,127 @@ const HELP: &str = r###"
     Help:
     Single Commands: 
         view -> show preview,
-        help -> show help, 
+        help -> show help,
         export -> prints the result
         quit -> back to your prompt
 
+    Files:
+    export [--format ls|json|dircolors|toml|lscolors|eza|eza-theme|lsd] [--output FILE] [--delta [LAYER]] [--minify] [--canonical] -> prints (or writes) the result,
+        --delta: only the entries that differ from the layers up to LAYER (default: base)
+        --minify: drops entries ls doesn't need (ex.: 'di=01;34', 'ca=') and redundant attributes ('00;31' -> '31')
+        --canonical: file types in fixed order, then the patterns sorted by key
+    export [..] --depth 16|256|truecolor -> replaces the colors by the nearest ones the terminal can show
+        (256: xterm cube and grays, 16: nearest color of the palette)
+    export --format dircolors --per-term -> sections for all terminals (16 colors), TERM *256col* and
+        COLORTERM truecolor, each downsampled, 'eval $(dircolors FILE)' picks the one of the terminal
+    import FILE [--format ls|json|dircolors|toml|lscolors|eza|eza-theme|lsd] -> replaces the current entries with the file content
+    export --format lscolors -> BSD/macOS LSCOLORS for 'ls -G' (di ln so pi ex bd cd su sg tw ow, 8 colors and bold),
+        warns about everything it can't hold (extensions, other file types, 256/rgb colors, styles)
+    export --format eza|eza-theme -> EZA_COLORS string or eza theme.yml with the file types eza shares with ls
+        (di fi ln pi so bd cd ex or), the extensions and the eza keys, the eza keys of $EZA_COLORS
+        are read at start as 'eza' layer (its file types and extensions don't change the ls entries)
+    import THEME.yml --format vivid --filetypes filetypes.yml -> the entries of a vivid theme with the
+        categories of the database ('set category:programming.source color ..', 'view --group-by category')
+    export --format lsd -> file-type section of lsd's colors.yaml (foreground colors of di fi ex ln or mi pi bd cd so do),
+        warns about the styles, backgrounds, file types and extensions it drops
+    save FILE -> writes a toml theme (metadata, notes, categories and readable colors)
+    load FILE -> replaces the current entries with a toml theme
+    theme [--name NAME] [--author AUTHOR] [--description TEXT] -> shows or sets the theme metadata
+    note [which] [text] -> attaches a note to an entry, ex.: 'note di "blue is hard to read"'
+
+    View options:
+    view [glob] [--key KEY].. [--ext | --builtin | --eza] [--sort key|order|color|hue] [--group-by none|color|category]
+        ex.: 'view *.t* --sort hue', 'view --key di --key ln', 'view --ext --group-by none'
+    view [..] --json -> prints the matching entries as json
+    view [..] --describe -> one sentence per entry, ex.: 'directory (di): bold, blue on default background'
+        the preview uses the colors the terminal supports (COLORTERM, TERM, terminfo) and is plain text
+        in pipes or with NO_COLOR, 'bash-colors --color always|never' overrides it (export is not affected)
+    view [..] --contrast [--min-contrast 4.5] [--background dark|light|both] -> shows the contrast ratio
+        of every entry against the terminal background and marks the ones below the minimum
+
     Change commands:
     set [which] [what] [value] -> updates an entry ex.: 'set fi color 31' (sets regular file to color red) 
         [which]: the LS_COLORS entry (fi, de, ex ..)
-        [what] : style | color | bg
+        [what] : style | color | bg | ref
         [value]: the value
+    add [which] [what] [value] -> adds a file extension (*.ext) or a style variable ($name)
+    remove [which] -> removes a file extension or style variable, unsets other entries
+
+    eza keys:
+    the keys only eza knows (permissions, sizes, users, dates, git ..) are edited with the prefix eza:,
+        ex.: 'set eza:ur color 33', 'set eza:da color 34', 'remove eza:gm', 'view --eza' lists them,
+        they are only exported with --format eza|eza-theme
+        ur uw ux ue gr gw gx tr tw tx su sf xa -> permissions, nb..nt ub..ut df ds -> sizes and devices,
+        uu uR un gu gR gn -> users and groups, lc lm -> links, ga gm gd gv gt gi gc Gm Go Gc Gd -> git,
+        xx da in bl hd oc lp cc bO sp mp -> other columns, im vi mu lo cr do co tm cm bu sc -> file classes
+
+    Variables:
+    variable -> shows the variable being edited, 'variable GREP_COLORS' switches to it
+        (LS_COLORS, GREP_COLORS, GCC_COLORS, TREE_COLORS, LF_COLORS), each keeps its own entries, layers and theme,
+        it starts from the defaults of the program and the environment variable
+    set rv flag on|off -> turns a flag of GREP_COLORS (rv, ne) on or off
+        export and import of the other variables: --format ls|json|toml, 'save'/'load' record the variable
+
+    lf paths (LF_COLORS only): a key starting with / or ~ is matched against the full path ('*' stays within
+        a component), a key ending with / only matches directories, by name unless it is a path,
+        ex.: 'add ~/Documents color 31', 'add .git/ color 32', 'add *.git/ style 01', 'add ~/src/*/ color 33'
+        they win over the file types and patterns, the last matching entry wins ('export --canonical' keeps their order)
+    view --match PATH -> the entry lf uses for PATH, a trailing / marks a directory, ex.: 'view --match ~/src/lf/'
+
+    Diff:
+    diff [A] [B] -> added, removed and changed entries from A to B (default: initial current)
+        A, B: current | initial (start of the session) | env ($LS_COLORS) | defaults (GNU dircolors) | FILE
+    diff [A] --against-defaults -> only the customizations of A (default: current) relative to the GNU defaults
+
+    Merge:
+    merge BASE OURS THEIRS [--interactive] -> merges the changes of both sides into the current map
+    merge BASE OURS THEIRS --output FILE [--format dircolors|toml] -> writes the result with conflict markers
+        style variables are resolved, conflicts keep our side unless resolved
+
+    Lint:
+    lint [--max-length N] [--min-contrast R] [--background dark|light|both] [--include-defaults] -> checks for
+        invisible text, low contrast, concealed or blinking entries, case duplicates, extensions that look like
+        di or ex, keys that never match, empty entries that turn off defaults, categories that look alike with
+        color blindness .. Entries left at the defaults of the program are skipped without --include-defaults
+        'bash-colors --lint' runs the check without prompt, exit code 0: ok, 1: warnings, 2: errors
+
+    Palette:
+    palette -> shows the colors the terminal uses for the 16 basic colors, text and background
+    palette use xterm|vga|tango -> replaces the palette with a preset
+    palette set [0-15|name|fg|bg] rrggbb -> changes one color, ex.: 'palette set bg ffffff' ('#' needs quotes)
+    palette import FILE [--format alacritty|kitty|xresources|foot|windows-terminal|iterm] -> uses the colors
+        of a terminal scheme, 'bash-colors --palette FILE' does it at start
+    palette query [--refresh] [--timeout MS] -> asks the terminal for its colors (OSC 4/10/11),
+        the answer is cached per terminal, 'bash-colors --query-palette' does it at start
+    view --rgb -> renders the entries with the palette colors (how they look in that terminal)
+    view --depth 16|256|truecolor -> renders the colors as a terminal with this depth shows them and marks
+        the entries whose colors collapse onto the same one
+    view --simulate protanopia|deuteranopia|tritanopia|achromatopsia -> renders the entries as seen
+        with a color vision deficiency, 'lint' reports the categories that look alike
+
+    Analyze:
+    analyze [--threshold 0.05] [--background dark|light|both] -> groups the entries that look nearly identical
+        (OKLab distance with the palette colors) across file classes and suggests a color to separate them
+
+    Layers:
+    the entries are composed from: defaults (what ls does on its own) < base < overlay < env ($LS_COLORS),
+    start with 'bash-colors --base FILE --overlay FILE', changes made at the prompt go on top
+    layers -> shows the stack and which layer each entry comes from
+    layers add FILE [--name NAME] [--position N] -> adds a layer, 'layers remove NAME' removes it
+
+    Categories:
+    category list -> shows the extension categories (archive, image, audio, video, document, source, backup ..)
+    category add [name] [*.ext].. -> adds extensions to a category, ex.: 'category add image *.avif'
+    category remove [name] [*.ext].. -> removes extensions from a category
+    set category:[name] [what] [value] -> updates the members in the list, ex.: 'set category:image color 35'
+    add category:[name] [what] [value] -> adds the members that are not in the list yet
+    remove category:[name] -> removes every member from the list
+    view --category [name] -> only shows the members, 'view --group-by category' groups by category
+
+    Style variables:
+    add $archive color 31 -> defines the variable $archive
+    set *.tar ref $archive -> *.tar uses the look of $archive ('set *.tar ref none' unlinks it)
+    set $archive style 01 -> updates every entry using $archive
 "###;
 
 const HELP_VALUES: &str = r###"
@@ -32,69 +378,107 @@ const HELP_VALUES: &str = r###"
 
 "###;
 pub fn set_action(map: &mut ColorMap, which: &str, what: &str, value: &str) -> Result<bool, Box<dyn std::error::Error>> {
-    if map.contains_key(which) {
-        match what {
-            "style" => {
-                let style_value = Style::from_str(value)?;
-                map.entry(which.to_string()).and_modify(|item| {
-                    item.style = Some(style_value);
-                });
-                return Ok(true);
-                
-            },
-            "color" => {
-                let color_value = Color::from_str(value)?;
-                map.entry(which.to_string()).and_modify(|item| {
-                    item.color = Some(color_value);
-                });
-                return Ok(true);
-            },
-            "bg" => {
-                let bg_value = Background::from_str(value)?;
-                map.entry(which.to_string()).and_modify(|item| {
-                    item.bg = Some(bg_value);
-                });
-                return Ok(true);
-            },
-            _ => return Err("invalid action..".into())
-        }
-    } else {
-       return  Err("Invalid key".into())
+    if map.schema.is_flag(which) {
+        let on = match (what, value) {
+            ("flag", "on") => true,
+            ("flag", "off") => false,
+            _ => return Err(format!("{} is a flag: 'set {} flag on|off'", which, which).into()),
+        };
+        map.set_flag(which, on);
+        return Ok(true);
+    }
+    // the eza keys are only in the map once they are set
+    let new_eza_key = map.schema.is_ls() && eza::is_eza_key(which) && !map.contains_key(which);
+    if !map.contains_key(which) && !new_eza_key {
+        return Err("Invalid key".into());
+    }
+    // the value is checked before the entry is touched, a failed set keeps the entry as it was
+    let (style, color, bg) = match what {
+        "style" => (Some(Style::from_str(value)?), None, None),
+        "color" => (None, Some(Color::from_str(value)?), None),
+        "bg" => (None, None, Some(Background::from_str(value)?)),
+        "ref" => (None, None, None),
+        _ => return Err("invalid action..".into()),
+    };
+    if new_eza_key {
+        map.set_values(which, None, None, None);
     }
-    
+    match what {
+        "ref" if value == "none" => map.detach(which),
+        "ref" => {
+            if let Err(e) = map.set_reference(which, value) {
+                if new_eza_key {
+                    map.remove(which);
+                }
+                return Err(e.into());
+            }
+        },
+        _ => {
+            map.detach(which);
+            map.entry(which.to_string()).and_modify(|item| {
+                item.style = style.or(item.style.take());
+                item.color = color.or(item.color.take());
+                item.bg = bg.or(item.bg.take());
+            });
+            map.copy_to_members(which);
+        },
+    }
+    Ok(true)
 }
 
 pub fn add_action(map: &mut ColorMap, which: &str, what: &str, value: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    if map.schema.paths && lf::is_path_key(which) {
+        lf::validate(which).map_err(|reason| format!("{} {}", which, reason))?;
+    }
     if map.contains_key(which) {
-        return Err(format!("item: {} already in the list", which).into());
-    } else if !which.starts_with("*.") {
-        return Err("Only file extensions can be added to the list (*.ext)".into());
+        Err(format!("item: {} already in the list", which).into())
+    } else if !map.schema.patterns && !is_variable(which) {
+        Err(format!("{} has no patterns, only style variables ($name) can be added to the list", map.schema.variable).into())
+    } else if !(which.starts_with("*.") || is_variable(which) || map.schema.paths && lf::is_path_key(which) || map.schema.is_ls() && eza::is_eza_key(which)) {
+        let others = if map.schema.paths { ", paths (~/Documents, /tmp/*) and directory names (.git/)" } else { " and eza keys (eza:ur)" };
+        Err(format!("Only file extensions (*.ext), style variables ($name){} can be added to the list", others).into())
     } else {
         let (style, color, bg) = match what {
             "style" => (Some(Style::from_str(value)?), None, None),
             "color" => (None, Some(Color::from_str(value)?), None),
             "bg" =>    (None, None, Some(Background::from_str(value)?)),
-            _ => return Err(format!("can't set {} for new item (valid: style, color, bg)", what).into())
+            "ref" => (None, None, None),
+            _ => return Err(format!("can't set {} for new item (valid: style, color, bg, ref)", what).into())
         };
         let item = LsItem {
             style, color, bg,
-            description: format!("file extension: {}", which),
+            description: default_description(which),
             order: map.len() + 1,
-
+            reference: None,
         };
         map.insert(which.to_string(), item);
+        if what == "ref" {
+            if let Err(e) = map.set_reference(which, value) {
+                map.remove(which);
+                return Err(e.into());
+            }
+        }
         Ok(true)
     }
 }
 pub fn remove_action(map: &mut ColorMap, which: &str) -> Result<bool, Box<dyn std::error::Error>> {
-    if which.starts_with("*.") {
+    if which.starts_with("*.") || eza::is_eza_key(which) || map.schema.paths && lf::is_path_key(which) {
         let r = map.remove(which);
         Ok(r.is_some())
+    } else if is_variable(which) && map.contains_key(which) {
+        // the users keep the look of the variable
+        let users: Vec<String> = map.users_of(which).iter().map(|k| k.to_string()).collect();
+        for user in users {
+            map.detach(&user);
+        }
+        map.remove(which);
+        Ok(true)
     } else if map.contains_key(which) {
         map.entry(which.to_string()).and_modify(|item| {
             item.bg = None;
             item.color = None;
             item.style = None;
+            item.reference = None;
         });
         Ok(true)
     } else {
@@ -102,6 +486,310 @@ pub fn remove_action(map: &mut ColorMap, which: &str) -> Result<bool, Box<dyn st
     }
 }
 
+pub fn set_category_action(map: &mut ColorMap, info: &ThemeInfo, name: &str, what: &str, value: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let members = categories::members(info, name).ok_or(format!("unknown category: {}", name))?;
+    let present: Vec<&String> = members.iter().filter(|key| map.contains_key(*key)).collect();
+    if present.is_empty() {
+        return Err(format!("no member of {} is in the list, 'add category:{} ..' adds them", name, name).into());
+    }
+    // all members or none: the change is made on a copy
+    let mut changed = map.clone();
+    for key in present {
+        set_action(&mut changed, key, what, value)?;
+    }
+    *map = changed;
+    Ok(true)
+}
+
+/// adds the members of the category that are not in the list yet
+pub fn add_category_action(map: &mut ColorMap, info: &ThemeInfo, name: &str, what: &str, value: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let members = categories::members(info, name).ok_or(format!("unknown category: {}", name))?;
+    let missing: Vec<&String> = members.iter().filter(|key| !map.contains_key(*key)).collect();
+    if missing.is_empty() {
+        return Err(format!("every member of {} is already in the list, use 'set category:{} ..'", name, name).into());
+    }
+    let mut changed = map.clone();
+    for key in &missing {
+        add_action(&mut changed, key, what, value)?;
+    }
+    *map = changed;
+    println!("added {} entries", missing.len());
+    Ok(true)
+}
+
+pub fn remove_category_action(map: &mut ColorMap, info: &ThemeInfo, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let members = categories::members(info, name).ok_or(format!("unknown category: {}", name))?;
+    for key in &members {
+        map.remove(key);
+    }
+    Ok(true)
+}
+
+pub fn category_action(map: &ColorMap, info: &mut ThemeInfo, action: &CategoryAction) -> Result<bool, Box<dyn std::error::Error>> {
+    match action {
+        CategoryAction::List => {
+            for (name, members) in categories::all(info) {
+                let used = members.iter().filter(|m| map.contains_key(*m)).count();
+                println!("{:<10} ({}/{} in list): {}", name, used, members.len(), members.join(" "));
+            }
+        },
+        CategoryAction::Add { name, keys } => categories::add_members(info, name, keys)?,
+        CategoryAction::Remove { name, keys } => categories::remove_members(info, name, keys)?,
+    }
+    Ok(true)
+}
+
+pub fn layers_action(map: &mut ColorMap, stack: &mut LayerStack, action: LayerAction) -> Result<bool, Box<dyn std::error::Error>> {
+    let schema = stack.schema;
+    *map = stack.rebuild(map, |layers| {
+        match action {
+            LayerAction::Add { file, name, position } => {
+                let name = name.unwrap_or_else(|| file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default());
+                if layers.iter().any(|l| l.name == name) {
+                    return Err(format!("layer {} already exists", name).into());
+                }
+                let layer = Layer::from_file(&name, &file, schema)?;
+                let below_env = layers.iter().position(|l| l.name == "env").unwrap_or(layers.len());
+                layers.insert(position.unwrap_or(below_env).min(layers.len()), layer);
+            },
+            LayerAction::Remove { name } => {
+                let position = layers.iter().position(|l| l.name == name).ok_or(format!("unknown layer: {}", name))?;
+                layers.remove(position);
+            },
+        }
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn palette_action(palette: &mut Palette, action: PaletteAction) -> Result<bool, Box<dyn std::error::Error>> {
+    match action {
+        PaletteAction::Use { name } => {
+            *palette = Palette::preset(&name).ok_or(format!("unknown palette: {} ({})", name, palette::PRESETS.join(", ")))?;
+        },
+        PaletteAction::Set { slot, value } => {
+            let rgb = palette::parse_hex(&value).ok_or(format!("invalid color: {} (rrggbb)", value))?;
+            palette.set(&slot, rgb)?;
+        },
+        PaletteAction::Import { file, format } => {
+            *palette = schemes::import_scheme(&file, format)?;
+        },
+        PaletteAction::Query { refresh, timeout } => {
+            let (live, cached) = query::live_palette(Duration::from_millis(timeout), refresh)
+                .map_err(|e| format!("cannot query the terminal ({}), keeping the {} palette", e, palette.name))?;
+            if cached {
+                println!("from the cache, 'palette query --refresh' asks the terminal again");
+            }
+            *palette = live;
+        },
+    }
+    Ok(true)
+}
+
+pub fn analyze_action(map: &ColorMap, info: &ThemeInfo, palette: &Palette, threshold: f64, background: TermBackground) -> Result<bool, Box<dyn std::error::Error>> {
+    let categories = categories::all(info);
+    for background in background.variants() {
+        let looks = analyze::looks(map, &categories, palette, background);
+        analyze::print_clusters(&analyze::clusters(&looks, threshold, palette, background), threshold, background);
+    }
+    Ok(true)
+}
+
+pub fn diff_action(current: &ColorMap, initial: &ColorMap, a: Option<&str>, b: Option<&str>, against_defaults: bool) -> Result<bool, Box<dyn std::error::Error>> {
+    let (from, to) = if against_defaults {
+        ("defaults", a.unwrap_or("current"))
+    } else {
+        (a.unwrap_or("initial"), b.unwrap_or("current"))
+    };
+    let from_map = diff::load_source(from, current, initial)?;
+    let to_map = diff::load_source(to, current, initial)?;
+    println!("{} -> {}", from, to);
+    diff::print_diff(&diff::diff_maps(&from_map, &to_map), against_defaults);
+    Ok(true)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn merge_action(map: &mut ColorMap, initial: &ColorMap, info: &ThemeInfo, sources: [&str; 3], interactive: bool, output: Option<&Path>, format: Format) -> Result<bool, Box<dyn std::error::Error>> {
+    let [base, ours, theirs] = sources.map(|source| diff::load_source(source, map, initial));
+    let (mut merged, conflicts) = merge::merge_maps(&base?, &ours?, &theirs?);
+    println!("{} conflicts", conflicts.len());
+
+    if let Some(path) = output {
+        let content = match format {
+            Format::Dircolors => merge::dircolors_with_conflicts(&merged, &conflicts),
+            Format::Toml => merge::toml_with_conflicts(&merged, info, &conflicts)?,
+            _ => return Err("conflict markers can only be written in dircolors or toml format".into()),
+        };
+        fs::write(path, content)?;
+        return Ok(true);
+    }
+    if interactive {
+        merge::resolve_interactive(&mut merged, &conflicts)?;
+    } else {
+        for conflict in &conflicts {
+            println!("kept ours for: {}", conflict.key);
+        }
+    }
+    *map = merged;
+    Ok(true)
+}
+
+fn export_content(map: &ColorMap, info: &ThemeInfo, palette: &Palette, format: Format) -> Result<String, Box<dyn std::error::Error>> {
+    let content = match format {
+        Format::Ls => format!("{}=\"{}\"", map.schema.variable, create_ls_string(map)),
+        Format::Json => json::map_to_json(map)?,
+        Format::Dircolors => dircolors::to_dircolors(map),
+        Format::Toml => theme::theme_to_string(map, info)?,
+        Format::Lscolors => format!("LSCOLORS=\"{}\"", lscolors::to_lscolors(map, palette).0),
+        Format::Eza => format!("EZA_COLORS=\"{}\"", eza::to_eza_colors(map).0),
+        Format::EzaTheme => eza::to_eza_theme(map)?.0,
+        Format::Lsd => lsd::to_lsd_colors(map)?.0,
+        Format::Vivid => return Err("vivid themes can only be imported".into()),
+    };
+    Ok(content)
+}
+
+/// the other variables are only written as string, json or toml theme
+fn check_format(schema: &Schema, format: Format) -> Result<(), String> {
+    if schema.is_ls() || matches!(format, Format::Ls | Format::Json | Format::Toml) {
+        Ok(())
+    } else {
+        Err(format!("{} can only be written as ls (the variable string), json or toml", schema.variable))
+    }
+}
+
+pub fn export_action(map: &ColorMap, info: &ThemeInfo, palette: &Palette, args: &ExportArgs) -> Result<bool, Box<dyn std::error::Error>> {
+    check_format(map.schema, args.format)?;
+    if args.per_term && args.format != Format::Dircolors {
+        return Err("--per-term needs --format dircolors".into());
+    }
+    if args.minify && matches!(args.format, Format::Eza | Format::EzaTheme | Format::Lsd) {
+        return Err("--minify only applies to the LS_COLORS formats".into());
+    }
+    let mut export_map = if args.minify { compact::minify(map) } else { map.clone() };
+    if args.canonical {
+        export_map = compact::canonicalize(&export_map);
+    }
+    if let Some(depth) = args.depth {
+        export_map = depth::downsample_map(&export_map, depth, palette);
+    }
+    let mut warnings = vec![];
+    let content = if args.per_term {
+        depth::to_dircolors_per_term(&export_map, palette)
+    } else if args.format == Format::Lscolors {
+        let (letters, lost) = lscolors::to_lscolors(&export_map, palette);
+        warnings = lost;
+        format!("LSCOLORS=\"{}\"", letters)
+    } else if args.format == Format::Eza {
+        let (colors, lost) = eza::to_eza_colors(&export_map);
+        warnings = lost;
+        format!("EZA_COLORS=\"{}\"", colors)
+    } else if args.format == Format::EzaTheme {
+        let (theme, lost) = eza::to_eza_theme(&export_map)?;
+        warnings = lost;
+        theme.trim_end().to_string()
+    } else if args.format == Format::Lsd {
+        let (colors, lost) = lsd::to_lsd_colors(&export_map)?;
+        warnings = lost;
+        colors.trim_end().to_string()
+    } else {
+        export_content(&export_map, info, palette, args.format)?
+    };
+    match &args.output {
+        Some(path) => fs::write(path, content.clone() + "\n")?,
+        None => println!("{}", content),
+    }
+    for warning in warnings {
+        println!("warning: {}", warning);
+    }
+    if args.minify {
+        let full = export_content(map, info, palette, args.format)?;
+        println!("{} -> {} bytes (saved {})", full.len(), content.len(), full.len().saturating_sub(content.len()));
+    }
+    Ok(true)
+}
+
+pub fn import_action(map: &mut ColorMap, info: &mut ThemeInfo, file: &Path, format: Format, filetypes: Option<&Path>) -> Result<bool, Box<dyn std::error::Error>> {
+    if filetypes.is_some() && format != Format::Vivid {
+        return Err("--filetypes only applies to --format vivid".into());
+    }
+    check_format(map.schema, format)?;
+    let content = fs::read_to_string(file)?;
+    let mut warnings = vec![];
+    *map = match format {
+        Format::Ls => {
+            // accepts the bare string as well as the output of `export`
+            let mut new_map = ColorMap::new(map.schema);
+            new_map.parse_env_string(layers::strip_variable(&content, map.schema).to_string());
+            new_map
+        },
+        Format::Json => json::map_from_json(&content, map.schema, ColorMap::new)?,
+        Format::Dircolors => {
+            let mut new_map = ColorMap::default();
+            dircolors::apply_dircolors(&mut new_map, &content);
+            new_map
+        },
+        Format::Toml => {
+            let (new_map, theme_info) = theme::load_theme(file)?;
+            if new_map.schema != map.schema {
+                return Err(format!("{} is not a theme for {}, switch with 'variable'", file.display(), map.schema.variable).into());
+            }
+            info.merge(theme_info);
+            new_map
+        },
+        Format::Lscolors => {
+            let mut new_map = ColorMap::default();
+            lscolors::apply_lscolors(&mut new_map, &content)?;
+            new_map
+        },
+        Format::Eza => {
+            let mut new_map = ColorMap::default();
+            let unknown = eza::apply_eza_colors(&mut new_map, &content);
+            if !unknown.is_empty() {
+                warnings.push(format!("{} are not eza keys and are skipped", unknown.join(", ")));
+            }
+            new_map
+        },
+        Format::EzaTheme => {
+            let mut new_map = ColorMap::default();
+            warnings = eza::apply_eza_theme(&mut new_map, &content)?;
+            new_map
+        },
+        Format::Lsd => {
+            let mut new_map = ColorMap::default();
+            warnings = lsd::apply_lsd_colors(&mut new_map, &content)?;
+            new_map
+        },
+        Format::Vivid => {
+            let filetypes = filetypes.ok_or("--format vivid needs the filetypes database: --filetypes FILE")?;
+            let import = vivid::import_vivid(&fs::read_to_string(filetypes)?, &content)?;
+            warnings = import.warnings;
+            // the categories of the database replace the ones with the same name
+            info.categories.extend(import.categories);
+            if info.name.is_empty() {
+                info.name = file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            }
+            import.map
+        },
+    };
+    for warning in warnings {
+        println!("warning: {}", warning);
+    }
+    Ok(true)
+}
+
+pub fn note_action(map: &ColorMap, info: &mut ThemeInfo, which: &str, text: &[String]) -> Result<bool, Box<dyn std::error::Error>> {
+    if !map.contains_key(which) {
+        return Err(format!("unknown entry: {}", which).into());
+    }
+    if text.is_empty() {
+        Ok(info.notes.remove(which).is_some())
+    } else {
+        info.notes.insert(which.to_string(), text.join(" "));
+        Ok(true)
+    }
+}
+
 pub fn print_help() {
     println!("{}", HELP);
     println!("{}", HELP_VALUES);
@@ -0,0 +1,93 @@
+use crate::{lf, ColorMap, LsItem, Style};
+
+/// keys matched against the end of the file name (*.tar, *~)
+fn is_pattern(key: &str) -> bool {
+    key.starts_with('*')
+}
+
+fn is_empty(item: &LsItem) -> bool {
+    item.style.is_none() && item.color.is_none() && item.bg.is_none()
+}
+
+/// a reset (00) in front of a color is what ls does anyway
+fn without_redundant_reset(item: &LsItem) -> LsItem {
+    let mut item = item.clone();
+    if item.style == Some(Style::None) && (item.color.is_some() || item.bg.is_some()) {
+        item.style = None;
+    }
+    item
+}
+
+/// same look as an entry without values (no color at all)
+fn looks_uncolored(item: &LsItem) -> bool {
+    is_empty(item) || (item.style == Some(Style::None) && item.color.is_none() && item.bg.is_none())
+}
+
+/// drops the entries that don't change what ls does on its own and the redundant attributes of the others,
+/// style variables are resolved
+pub fn minify(map: &ColorMap) -> ColorMap {
+    let mut builtin = ColorMap::empty_for(map.schema);
+    builtin.parse_env_string(map.schema.builtin.to_string());
+
+    let flat = map.flatten(false);
+    let mut minified = ColorMap::empty_for(map.schema);
+    for (key, item) in flat.iter() {
+        let item = without_redundant_reset(item);
+        let redundant = match builtin.get(key) {
+            Some(default) => default.same_values(&item) || (looks_uncolored(default) && looks_uncolored(&item)),
+            // lf paths shadow the file types and patterns, even without values
+            None if lf::is_path_key(key) => false,
+            // flags have no values, an unset one is not written anyway
+            None if map.schema.is_flag(key) => !flat.flag_is_on(key),
+            None if is_pattern(key) => {
+                // an empty extension still matters when it shadows a shorter pattern (*.tar.gz= vs *.gz)
+                looks_uncolored(&item) && !flat.keys().any(|other| other != key && is_pattern(other) && key.ends_with(other.trim_start_matches('*')))
+            },
+            None => looks_uncolored(&item),
+        };
+        if !redundant {
+            minified.insert(key.clone(), item);
+        }
+    }
+    minified
+}
+
+/// renumbers the entries: the file types in the order of the built-in list, then the patterns sorted by key,
+/// then the lf paths in their order (the last matching one wins)
+pub fn canonicalize(map: &ColorMap) -> ColorMap {
+    let defaults = ColorMap::new(map.schema);
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort_by(|a, b| {
+        let rank = |key: &String| match defaults.get(key) {
+            Some(item) => (0, item.order, String::new()),
+            None if lf::is_path_key(key) => (2, map[key].order, String::new()),
+            None => (1, 0, key.to_lowercase()),
+        };
+        rank(a).cmp(&rank(b)).then(a.cmp(b))
+    });
+    let mut canonical = ColorMap::empty_for(map.schema);
+    for (i, key) in keys.into_iter().enumerate() {
+        let mut item = map[key].clone();
+        item.order = i + 1;
+        canonical.insert(key.clone(), item);
+    }
+    canonical
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema;
+
+    #[test]
+    fn minify_keeps_the_flags_that_are_on() {
+        let mut map = ColorMap::empty_for(&schema::GREP_COLORS);
+        map.parse_env_string(schema::GREP_COLORS.builtin.to_string());
+        map.parse_env_string("rv:ne".to_string());
+        map.set_flag("ne", false);
+        let minified = minify(&map);
+        assert!(minified.flag_is_on("rv"));
+        assert!(!minified.contains_key("ne"));
+        assert_eq!(crate::create_ls_string(&minified), "rv");
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::{BTreeMap, HashMap};
+
+use clap::ValueEnum;
+
+use crate::palette::{delinearize, linearize, oklab_distance, Palette, Rgb, TermBackground};
+use crate::{ColorMap, LsItem, Style};
+
+/// color vision deficiencies
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Deficiency {
+    /// no red cones
+    Protanopia,
+    /// no green cones
+    Deuteranopia,
+    /// no blue cones
+    Tritanopia,
+    /// no color vision at all
+    Achromatopsia,
+}
+
+pub const ALL: [Deficiency; 4] = [Deficiency::Protanopia, Deficiency::Deuteranopia, Deficiency::Tritanopia, Deficiency::Achromatopsia];
+
+/// below this OKLab distance two colors are hard to tell apart
+pub const CONFUSABLE: f64 = 0.06;
+
+impl Deficiency {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Protanopia => "protanopia",
+            Self::Deuteranopia => "deuteranopia",
+            Self::Tritanopia => "tritanopia",
+            Self::Achromatopsia => "achromatopsia",
+        }
+    }
+
+    /// matrices on linear rgb (Machado, Oliveira and Fernandes 2009, severity 1)
+    fn matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            Self::Protanopia => [[0.152286, 1.052583, -0.204868], [0.114503, 0.786281, 0.099216], [-0.003882, -0.048116, 1.051998]],
+            Self::Deuteranopia => [[0.367322, 0.860646, -0.227968], [0.280085, 0.672501, 0.047413], [-0.011820, 0.042940, 0.968881]],
+            Self::Tritanopia => [[1.255528, -0.076749, -0.178779], [-0.078411, 0.930809, 0.147602], [0.004733, 0.691367, 0.303900]],
+            // every channel gets the luminance
+            Self::Achromatopsia => [[0.2126, 0.7152, 0.0722]; 3],
+        }
+    }
+
+    /// how the color looks with this deficiency
+    pub fn simulate(self, rgb: Rgb) -> Rgb {
+        let linear = [linearize(rgb.0), linearize(rgb.1), linearize(rgb.2)];
+        let m = self.matrix();
+        let channel = |row: [f64; 3]| delinearize(row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]);
+        (channel(m[0]), channel(m[1]), channel(m[2]))
+    }
+}
+
+/// the look shared by most colored members of a category (ties go to the first in order)
+fn category_look<'a>(map: &'a ColorMap, members: &[String]) -> Option<&'a LsItem> {
+    let mut counts: HashMap<String, (usize, &LsItem)> = HashMap::new();
+    for item in members.iter().filter_map(|key| map.get(key)).filter(|item| item.color.is_some() || item.bg.is_some()) {
+        let entry = counts.entry(item.to_string()).or_insert((0, item));
+        entry.0 += 1;
+        if item.order < entry.1.order {
+            entry.1 = item;
+        }
+    }
+    counts.into_values().max_by(|a, b| a.0.cmp(&b.0).then(b.1.order.cmp(&a.1.order))).map(|(_, item)| item)
+}
+
+/// true when both looks can't be told apart once their colors went through `transform`
+fn confusable(palette: &Palette, a: &LsItem, b: &LsItem, transform: impl Fn(Rgb) -> Rgb) -> bool {
+    let bold = |item: &LsItem| item.style == Some(Style::Bold);
+    if bold(a) != bold(b) || (a.style == Some(Style::Underscore)) != (b.style == Some(Style::Underscore)) {
+        return false;
+    }
+    let terminal = palette.terminal_colors(TermBackground::Dark);
+    let (fg_a, bg_a) = palette.effective_colors(a, terminal);
+    let (fg_b, bg_b) = palette.effective_colors(b, terminal);
+    oklab_distance(transform(fg_a), transform(fg_b)) < CONFUSABLE && oklab_distance(transform(bg_a), transform(bg_b)) < CONFUSABLE
+}
+
+/// pairs of categories that look different with normal vision but alike with the deficiency
+pub fn confusable_categories(map: &ColorMap, categories: &BTreeMap<String, Vec<String>>, palette: &Palette, deficiency: Deficiency) -> Vec<(String, String)> {
+    let flat = map.flatten(false);
+    let looks: Vec<(&String, &LsItem)> = categories.iter().filter_map(|(name, members)| Some((name, category_look(&flat, members)?))).collect();
+    let mut pairs = vec![];
+    for (i, (name_a, a)) in looks.iter().enumerate() {
+        for (name_b, b) in &looks[i + 1..] {
+            if !confusable(palette, a, b, |rgb| rgb) && confusable(palette, a, b, |rgb| deficiency.simulate(rgb)) {
+                pairs.push((name_a.to_string(), name_b.to_string()));
+            }
+        }
+    }
+    pairs
+}
@@ -0,0 +1,108 @@
+use clap::ValueEnum;
+
+use crate::palette::{oklab_distance, Palette, Rgb};
+use crate::{ansi_256_rgb, dircolors, Background, Color, ColorMap, LsItem};
+
+/// how many colors a terminal can show
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Depth {
+    /// the 16 basic colors (30-37, 90-97)
+    #[value(name = "16")]
+    Ansi16,
+    /// the xterm 256 color palette (38;5;n)
+    #[value(name = "256")]
+    Ansi256,
+    /// 24 bit colors (38;2;r;g;b)
+    Truecolor,
+}
+
+impl Depth {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ansi16 => "16",
+            Self::Ansi256 => "256",
+            Self::Truecolor => "truecolor",
+        }
+    }
+}
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// nearest color of the 6x6x6 cube (16 - 231) or the grayscale ramp (232 - 255)
+pub fn nearest_256(rgb: Rgb) -> u8 {
+    let level = |c: u8| CUBE_LEVELS.iter().enumerate().min_by_key(|(_, l)| (**l as i16 - c as i16).abs()).map(|(i, _)| i as u8).unwrap();
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+    let average = (rgb.0 as u16 + rgb.1 as u16 + rgb.2 as u16) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if oklab_distance(rgb, ansi_256_rgb(gray)) < oklab_distance(rgb, ansi_256_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// nearest of the 16 colors as the palette shows them
+pub fn nearest_16(rgb: Rgb, palette: &Palette) -> u8 {
+    palette.ansi.iter().enumerate().min_by(|(_, a), (_, b)| oklab_distance(rgb, **a).total_cmp(&oklab_distance(rgb, **b))).map(|(i, _)| i as u8).unwrap()
+}
+
+/// the color a terminal with `depth` can show
+pub fn downsample(color: &Color, depth: Depth, palette: &Palette) -> Color {
+    match (color, depth) {
+        (Color::_Ansi(n), _) if *n < 16 => Color::from_index(*n),
+        (Color::_RGB(rgb), Depth::Ansi256) => Color::_Ansi(nearest_256(*rgb)),
+        (Color::_RGB(_) | Color::_Ansi(_), Depth::Ansi16) => Color::from_index(nearest_16(palette.rgb(color), palette)),
+        _ => color.clone(),
+    }
+}
+
+pub fn downsample_item(item: &LsItem, depth: Depth, palette: &Palette) -> LsItem {
+    let mut item = item.clone();
+    item.color = item.color.map(|c| downsample(&c, depth, palette));
+    item.bg = item.bg.map(|b| Background::from_color(&downsample(&b.to_color(), depth, palette)));
+    item
+}
+
+/// the map with every color replaced by the nearest one the terminal can show
+pub fn downsample_map(map: &ColorMap, depth: Depth, palette: &Palette) -> ColorMap {
+    let mut downsampled = map.clone();
+    for item in downsampled.values_mut() {
+        *item = downsample_item(item, depth, palette);
+    }
+    downsampled
+}
+
+/// dircolors database with one section per terminal class, from the least to the most capable:
+/// dircolors applies every section whose TERM or COLORTERM matches, so the last matching one wins.
+/// the 256 color and truecolor sections hold every entry that differs from the 16 color section,
+/// a terminal matching only the truecolor section doesn't miss the changes of the 256 color one
+pub fn to_dircolors_per_term(map: &ColorMap, palette: &Palette) -> String {
+    let map = map.flatten(false);
+    let mut entries: Vec<(&String, &LsItem)> = map.iter().collect();
+    entries.sort_by_key(|(_, item)| item.order);
+
+    let sections: [(Depth, &str, &[&str]); 3] = [
+        (Depth::Ansi16, "all terminals: 16 colors", &["TERM *"]),
+        (Depth::Ansi256, "256 color terminals", &["TERM *256col*", "TERM xterm-kitty", "TERM alacritty", "TERM foot*", "TERM wezterm"]),
+        (Depth::Truecolor, "truecolor terminals", &["COLORTERM truecolor", "COLORTERM 24bit"]),
+    ];
+    let mut lines = vec![];
+    let mut base: Option<Vec<LsItem>> = None;
+    for (depth, title, conditions) in sections {
+        let items: Vec<LsItem> = entries.iter().map(|(_, item)| downsample_item(item, depth, palette)).collect();
+        let section: Vec<String> = entries.iter().zip(&items).enumerate()
+            .filter(|(i, _)| base.as_ref().is_none_or(|b| !b[*i].same_values(&items[*i])))
+            .filter_map(|(_, ((key, _), item))| dircolors::entry_line(key, item))
+            .collect();
+        if base.is_none() || !section.is_empty() {
+            lines.push(format!("# {}", title));
+            lines.extend(conditions.iter().map(|c| c.to_string()));
+            lines.extend(section);
+            lines.push(String::new());
+        }
+        if base.is_none() {
+            base = Some(items);
+        }
+    }
+    lines.join("\n")
+}
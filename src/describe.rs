@@ -0,0 +1,83 @@
+use crate::palette::{oklab_distance, Palette, Rgb};
+use crate::{default_description, Background, Color, LsItem, Style, COLOR_NAMES};
+
+/// common color names used to describe 256 and rgb colors
+const NAMED: &[(&str, Rgb)] = &[
+    ("black", (0, 0, 0)),
+    ("dark gray", (85, 85, 85)),
+    ("gray", (128, 128, 128)),
+    ("light gray", (192, 192, 192)),
+    ("white", (255, 255, 255)),
+    ("red", (220, 20, 20)),
+    ("dark red", (139, 0, 0)),
+    ("pink", (255, 150, 190)),
+    ("orange", (255, 140, 0)),
+    ("brown", (150, 75, 0)),
+    ("gold", (255, 200, 0)),
+    ("yellow", (255, 255, 0)),
+    ("olive", (128, 128, 0)),
+    ("lime", (120, 255, 0)),
+    ("green", (0, 170, 0)),
+    ("dark green", (0, 90, 0)),
+    ("teal", (0, 128, 128)),
+    ("cyan", (0, 255, 255)),
+    ("sky blue", (100, 180, 255)),
+    ("blue", (0, 60, 255)),
+    ("navy", (0, 0, 128)),
+    ("purple", (128, 0, 160)),
+    ("violet", (190, 100, 255)),
+    ("magenta", (255, 0, 255)),
+];
+
+fn nearest_name(rgb: Rgb) -> &'static str {
+    NAMED.iter().min_by(|a, b| oklab_distance(rgb, a.1).total_cmp(&oklab_distance(rgb, b.1))).map(|(name, _)| *name).unwrap()
+}
+
+/// 'bright blue', 'orange (color 208)', 'sky blue (#1e90ff)'
+pub fn color_words(color: &Color, palette: &Palette) -> String {
+    match color {
+        Color::_Ansi(n) if *n < 16 => COLOR_NAMES[*n as usize].replace('-', " "),
+        Color::_Ansi(n) => format!("{} (color {})", nearest_name(palette.rgb(color)), n),
+        Color::_RGB((r, g, b)) => format!("{} (#{:02x}{:02x}{:02x})", nearest_name((*r, *g, *b)), r, g, b),
+        basic => COLOR_NAMES[basic.index().unwrap() as usize].replace('-', " "),
+    }
+}
+
+pub fn style_words(style: &Style) -> &'static str {
+    match style {
+        Style::None => "plain",
+        Style::Bold => "bold",
+        Style::Underscore => "underlined",
+        Style::Blink => "blinking",
+        Style::Reverse => "reversed",
+        Style::Concealed => "hidden",
+    }
+}
+
+/// the look in words: 'bold, bright blue on default background'
+pub fn look_words(item: &LsItem, palette: &Palette) -> String {
+    if item.color.is_none() && item.bg.is_none() {
+        return item.style.as_ref().map_or("not colored", style_words).to_string();
+    }
+    let mut words = vec![];
+    if let Some(style) = item.style.as_ref().filter(|s| **s != Style::None) {
+        words.push(style_words(style).to_string());
+    }
+    words.push(item.color.as_ref().map_or("default color".to_string(), |c| color_words(c, palette)));
+    let background = item.bg.as_ref().map_or("default background".to_string(), |bg: &Background| format!("{} background", color_words(&bg.to_color(), palette)));
+    format!("{} on {}", words.join(", "), background)
+}
+
+/// 'directory (di): bold, bright blue on default background'
+pub fn describe_entry(key: &str, item: &LsItem, palette: &Palette) -> String {
+    // 'files ending in .tar' reads better than the generated 'file extension: *.tar'
+    let what = match key.strip_prefix('*') {
+        Some(suffix) if item.description == default_description(key) => format!("files ending in {}", suffix),
+        _ => item.description.clone(),
+    };
+    let mut line = format!("{} ({}): {}", what, key, look_words(item, palette));
+    if let Some(variable) = &item.reference {
+        line.push_str(&format!(", from {}", variable));
+    }
+    line
+}
@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use crate::{dircolors, layers, ColorMap, LsItem};
+
+#[derive(Debug)]
+pub enum Change {
+    Added(LsItem),
+    Removed(LsItem),
+    Changed(LsItem, LsItem),
+}
+
+/// the differences from `a` to `b`, compared by look (variables are resolved), in declared order of `b`
+pub fn diff_maps(a: &ColorMap, b: &ColorMap) -> Vec<(String, Change)> {
+    let (a, b) = (a.flatten(false), b.flatten(false));
+    let mut changes = vec![];
+    for (key, item_b) in b.iter() {
+        match a.get(key) {
+            None => changes.push((key.clone(), Change::Added(item_b.clone()))),
+            Some(item_a) if !item_a.same_look(item_b) => changes.push((key.clone(), Change::Changed(item_a.clone(), item_b.clone()))),
+            Some(_) => (),
+        }
+    }
+    for (key, item_a) in a.iter() {
+        if !b.contains_key(key) {
+            changes.push((key.clone(), Change::Removed(item_a.clone())));
+        }
+    }
+    changes.sort_by_key(|(key, change)| match change {
+        Change::Removed(item) => (1, item.order, key.clone()),
+        Change::Added(item) | Change::Changed(_, item) => (0, item.order, key.clone()),
+    });
+    changes
+}
+
+/// resolves a diff operand: 'current', 'initial' (start of the session), 'env', 'defaults' (GNU dircolors, what
+/// the program does on its own for the other variables) or a file
+pub fn load_source(name: &str, current: &ColorMap, initial: &ColorMap) -> Result<ColorMap, Box<dyn std::error::Error>> {
+    match name {
+        "current" => Ok(current.clone()),
+        "initial" => Ok(initial.clone()),
+        "defaults" if !current.schema.is_ls() => {
+            let mut map = ColorMap::new(current.schema);
+            map.parse_env_string(current.schema.builtin.to_string());
+            Ok(map)
+        },
+        "defaults" => Ok(dircolors::gnu_defaults()),
+        "env" => {
+            let mut map = ColorMap::new(current.schema);
+            map.parse_env_string(std::env::var(current.schema.variable).map_err(|_| format!("${} is not set", current.schema.variable))?);
+            Ok(map)
+        },
+        file => layers::read_map_file(Path::new(file), current.schema, ColorMap::new),
+    }
+}
+
+/// colored sample of the values, padded so that the columns line up
+pub fn sample(item: &LsItem) -> String {
+    let values = item.to_string();
+    let text = if values.is_empty() { "(none)".to_string() } else { values };
+    format!("{}{}", item.color_helper(&text), " ".repeat(18usize.saturating_sub(text.len())))
+}
+
+pub fn print_diff(changes: &[(String, Change)], only_customizations: bool) {
+    let mut removed = 0;
+    for (key, change) in changes {
+        match change {
+            Change::Added(item) => println!("+ {:<14} {:<18} {}", key, "", sample(item)),
+            Change::Changed(a, b) => println!("~ {:<14} {} {}", key, sample(a), sample(b)),
+            Change::Removed(item) => {
+                removed += 1;
+                if !only_customizations {
+                    println!("- {:<14} {}", key, sample(item));
+                }
+            },
+        }
+    }
+    let added = changes.iter().filter(|(_, c)| matches!(c, Change::Added(_))).count();
+    let changed = changes.iter().filter(|(_, c)| matches!(c, Change::Changed(..))).count();
+    if only_customizations {
+        println!("{} added, {} changed ({} default entries not set)", added, changed, removed);
+    } else {
+        println!("{} added, {} removed, {} changed", added, removed, changed);
+    }
+}
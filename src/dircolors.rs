@@ -0,0 +1,275 @@
+use crate::{decode_values, ColorMap, LsItem};
+
+/// dircolors keywords and the LS_COLORS keys they stand for
+pub const KEYWORDS: &[(&str, &str)] = &[
+    ("RESET", "rs"),
+    ("NORMAL", "no"),
+    ("NORM", "no"),
+    ("FILE", "fi"),
+    ("DIR", "di"),
+    ("LINK", "ln"),
+    ("LNK", "ln"),
+    ("SYMLINK", "ln"),
+    ("MULTIHARDLINK", "mh"),
+    ("FIFO", "pi"),
+    ("PIPE", "pi"),
+    ("SOCK", "so"),
+    ("DOOR", "do"),
+    ("BLK", "bd"),
+    ("BLOCK", "bd"),
+    ("CHR", "cd"),
+    ("CHAR", "cd"),
+    ("ORPHAN", "or"),
+    ("MISSING", "mi"),
+    ("SETUID", "su"),
+    ("SUID", "su"),
+    ("SETGID", "sg"),
+    ("SGID", "sg"),
+    ("CAPABILITY", "ca"),
+    ("STICKY_OTHER_WRITABLE", "tw"),
+    ("OWT", "tw"),
+    ("OTHER_WRITABLE", "ow"),
+    ("OWR", "ow"),
+    ("STICKY", "st"),
+    ("EXEC", "ex"),
+];
+
+/// the LS_COLORS key for a dircolors line key ('DIR' -> 'di', '.tar' -> '*.tar')
+fn ls_key(word: &str) -> Option<String> {
+    if let Some((_, key)) = KEYWORDS.iter().find(|(k, _)| k.eq_ignore_ascii_case(word)) {
+        return Some(key.to_string());
+    }
+    if word.starts_with('.') {
+        return Some(format!("*{}", word));
+    }
+    if word.starts_with('*') {
+        return Some(word.to_string());
+    }
+    None
+}
+
+/// the dircolors line of an entry ('di' -> 'DIR 01;34', '*.tar' -> '.tar 01;31'), entries without values are skipped
+pub fn entry_line(key: &str, item: &LsItem) -> Option<String> {
+    let values = item.to_string();
+    if values.is_empty() {
+        return None;
+    }
+    let word = match KEYWORDS.iter().find(|(_, k)| *k == key) {
+        Some((keyword, _)) => keyword.to_string(),
+        None => key.strip_prefix('*').filter(|k| k.starts_with('.')).unwrap_or(key).to_string(),
+    };
+    Some(format!("{} {}", word, values))
+}
+
+/// the map as dircolors database, style variables are resolved
+pub fn to_dircolors(map: &ColorMap) -> String {
+    let map = map.flatten(false);
+    let mut entries: Vec<(&String, &LsItem)> = map.iter().collect();
+    entries.sort_by_key(|(_, item)| item.order);
+    let mut lines: Vec<String> = entries.iter().filter_map(|(key, item)| entry_line(key, item)).collect();
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// key / value pairs of a dircolors database, TERM and COLORTERM sections are not evaluated
+pub fn parse_dircolors(content: &str) -> Vec<(String, String)> {
+    let mut entries = vec![];
+    for line in content.lines() {
+        // '#' starts a comment at the beginning of the line or after whitespace ('*#' is a key)
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let line = line.find(" #").or(line.find("\t#")).map_or(line, |i| &line[..i]);
+        let mut words = line.split_whitespace();
+        let (Some(word), Some(value)) = (words.next(), words.next()) else {
+            continue;
+        };
+        if let Some(key) = ls_key(word) {
+            entries.push((key, value.to_string()));
+        }
+    }
+    entries
+}
+
+/// applies a dircolors database on top of the map
+pub fn apply_dircolors(map: &mut ColorMap, content: &str) {
+    for (key, value) in parse_dircolors(content) {
+        let (style, fg, bg) = decode_values(&value);
+        map.set_values(&key, style, fg, bg);
+    }
+}
+
+/// the database printed by `dircolors --print-database` (GNU coreutils), without the TERM list
+pub const GNU_DEFAULTS: &str = r###"
+# file types
+RESET 0
+DIR 01;34
+LINK 01;36
+MULTIHARDLINK 00
+FIFO 40;33
+SOCK 01;35
+DOOR 01;35
+BLK 40;33;01
+CHR 40;33;01
+ORPHAN 40;31;01
+MISSING 00
+SETUID 37;41
+SETGID 30;43
+CAPABILITY 00
+STICKY_OTHER_WRITABLE 30;42
+OTHER_WRITABLE 34;42
+STICKY 37;44
+EXEC 01;32
+# archives or compressed (bright red)
+.7z 01;31
+.ace 01;31
+.alz 01;31
+.apk 01;31
+.arc 01;31
+.arj 01;31
+.bz 01;31
+.bz2 01;31
+.cab 01;31
+.cpio 01;31
+.crate 01;31
+.deb 01;31
+.drpm 01;31
+.dwm 01;31
+.dz 01;31
+.ear 01;31
+.egg 01;31
+.esd 01;31
+.gz 01;31
+.jar 01;31
+.lha 01;31
+.lrz 01;31
+.lz 01;31
+.lz4 01;31
+.lzh 01;31
+.lzma 01;31
+.lzo 01;31
+.pyz 01;31
+.rar 01;31
+.rpm 01;31
+.rz 01;31
+.sar 01;31
+.swm 01;31
+.t7z 01;31
+.tar 01;31
+.taz 01;31
+.tbz 01;31
+.tbz2 01;31
+.tgz 01;31
+.tlz 01;31
+.txz 01;31
+.tz 01;31
+.tzo 01;31
+.tzst 01;31
+.udeb 01;31
+.war 01;31
+.whl 01;31
+.wim 01;31
+.xz 01;31
+.z 01;31
+.zip 01;31
+.zoo 01;31
+.zst 01;31
+# image formats
+.avif 01;35
+.jpg 01;35
+.jpeg 01;35
+.mjpg 01;35
+.mjpeg 01;35
+.gif 01;35
+.bmp 01;35
+.pbm 01;35
+.pgm 01;35
+.ppm 01;35
+.tga 01;35
+.xbm 01;35
+.xpm 01;35
+.tif 01;35
+.tiff 01;35
+.png 01;35
+.svg 01;35
+.svgz 01;35
+.mng 01;35
+.pcx 01;35
+.mov 01;35
+.mpg 01;35
+.mpeg 01;35
+.m2v 01;35
+.mkv 01;35
+.webm 01;35
+.webp 01;35
+.ogm 01;35
+.mp4 01;35
+.m4v 01;35
+.mp4v 01;35
+.vob 01;35
+.qt 01;35
+.nuv 01;35
+.wmv 01;35
+.asf 01;35
+.rm 01;35
+.rmvb 01;35
+.flc 01;35
+.avi 01;35
+.fli 01;35
+.flv 01;35
+.gl 01;35
+.dl 01;35
+.xcf 01;35
+.xwd 01;35
+.yuv 01;35
+.cgm 01;35
+.emf 01;35
+.ogv 01;35
+.ogx 01;35
+# audio formats
+.aac 00;36
+.au 00;36
+.flac 00;36
+.m4a 00;36
+.mid 00;36
+.midi 00;36
+.mka 00;36
+.mp3 00;36
+.mpc 00;36
+.ogg 00;36
+.ra 00;36
+.wav 00;36
+.oga 00;36
+.opus 00;36
+.spx 00;36
+.xspf 00;36
+# backup files
+*~ 00;90
+*# 00;90
+.bak 00;90
+.crdownload 00;90
+.dpkg-dist 00;90
+.dpkg-new 00;90
+.dpkg-old 00;90
+.dpkg-tmp 00;90
+.old 00;90
+.orig 00;90
+.part 00;90
+.rej 00;90
+.rpmnew 00;90
+.rpmorig 00;90
+.rpmsave 00;90
+.swp 00;90
+.tmp 00;90
+.ucf-dist 00;90
+.ucf-new 00;90
+.ucf-old 00;90
+"###;
+
+/// the GNU dircolors defaults as map
+pub fn gnu_defaults() -> ColorMap {
+    let mut map = ColorMap::default();
+    apply_dircolors(&mut map, GNU_DEFAULTS);
+    map
+}
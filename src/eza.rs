@@ -0,0 +1,352 @@
+use serde_yaml::{Mapping, Value};
+
+use crate::palette::parse_hex;
+use crate::{decode_values, is_variable, Background, Color, ColorMap, LsItem, Style, Values};
+
+/// eza keys live in the same map as the LS_COLORS entries under this prefix,
+/// several codes (su, tw, do) mean something else in eza than in LS_COLORS
+pub const PREFIX: &str = "eza:";
+
+/// file types eza reads with the same code as LS_COLORS, with their place in theme.yml
+const FILE_KINDS: [(&str, &str); 9] = [
+    ("fi", "filekinds.normal"),
+    ("di", "filekinds.directory"),
+    ("ln", "filekinds.symlink"),
+    ("pi", "filekinds.pipe"),
+    ("bd", "filekinds.block_device"),
+    ("cd", "filekinds.char_device"),
+    ("so", "filekinds.socket"),
+    ("ex", "filekinds.executable"),
+    ("or", "broken_symlink"),
+];
+
+/// the keys only eza knows: EZA_COLORS code, place in theme.yml and description
+pub const KEYS: &[(&str, &str, &str)] = &[
+    ("sp", "filekinds.special", "special file"),
+    ("mp", "filekinds.mount_point", "mount point"),
+    ("ur", "perms.user_read", "user read permission"),
+    ("uw", "perms.user_write", "user write permission"),
+    ("ux", "perms.user_execute_file", "user execute permission of regular files"),
+    ("ue", "perms.user_execute_other", "user execute permission of other file types"),
+    ("gr", "perms.group_read", "group read permission"),
+    ("gw", "perms.group_write", "group write permission"),
+    ("gx", "perms.group_execute", "group execute permission"),
+    ("tr", "perms.other_read", "others read permission"),
+    ("tw", "perms.other_write", "others write permission"),
+    ("tx", "perms.other_execute", "others execute permission"),
+    ("su", "perms.special_user_file", "setuid, setgid and sticky bits of regular files"),
+    ("sf", "perms.special_other", "setuid, setgid and sticky bits of other file types"),
+    ("xa", "perms.attribute", "extended attribute marker"),
+    ("nb", "size.number_byte", "file size number, bytes"),
+    ("nk", "size.number_kilo", "file size number, kilobytes"),
+    ("nm", "size.number_mega", "file size number, megabytes"),
+    ("ng", "size.number_giga", "file size number, gigabytes"),
+    ("nt", "size.number_huge", "file size number, terabytes and more"),
+    ("ub", "size.unit_byte", "file size unit, bytes"),
+    ("uk", "size.unit_kilo", "file size unit, kilobytes"),
+    ("um", "size.unit_mega", "file size unit, megabytes"),
+    ("ug", "size.unit_giga", "file size unit, gigabytes"),
+    ("ut", "size.unit_huge", "file size unit, terabytes and more"),
+    ("df", "size.major", "major device number"),
+    ("ds", "size.minor", "minor device number"),
+    ("uu", "users.user_you", "user that is you"),
+    ("uR", "users.user_root", "user that is root"),
+    ("un", "users.user_other", "user that is someone else"),
+    ("gu", "users.group_yours", "group you belong to"),
+    ("gR", "users.group_root", "group of root"),
+    ("gn", "users.group_other", "group you don't belong to"),
+    ("lc", "links.normal", "number of hard links"),
+    ("lm", "links.multi_link_file", "number of hard links above one"),
+    ("ga", "git.new", "git: new file"),
+    ("gm", "git.modified", "git: modified file"),
+    ("gd", "git.deleted", "git: deleted file"),
+    ("gv", "git.renamed", "git: renamed file"),
+    ("gt", "git.typechange", "git: file type changed"),
+    ("gi", "git.ignored", "git: ignored file"),
+    ("gc", "git.conflicted", "git: conflicted file"),
+    ("Gm", "git_repo.branch_main", "git repository on the main branch"),
+    ("Go", "git_repo.branch_other", "git repository on another branch"),
+    ("Gc", "git_repo.git_clean", "git repository without changes"),
+    ("Gd", "git_repo.git_dirty", "git repository with changes"),
+    ("xx", "punctuation", "punctuation (dashes, separators)"),
+    ("da", "date", "timestamp"),
+    ("in", "inode", "inode number"),
+    ("bl", "blocks", "number of blocks"),
+    ("hd", "header", "table header row"),
+    ("oc", "octal", "octal permissions"),
+    ("lp", "symlink_path", "path of a symlink target"),
+    ("cc", "control_char", "escaped control character in a file name"),
+    ("bO", "broken_path_overlay", "overlay on the path of a broken symlink"),
+    ("im", "file_type.image", "image file"),
+    ("vi", "file_type.video", "video file"),
+    ("mu", "file_type.music", "lossy music file"),
+    ("lo", "file_type.lossless", "lossless music file"),
+    ("cr", "file_type.crypto", "cryptographic file (keys, signatures)"),
+    ("do", "file_type.document", "document"),
+    ("co", "file_type.compressed", "compressed file or archive"),
+    ("tm", "file_type.temp", "temporary file"),
+    ("cm", "file_type.compiled", "compiled file"),
+    ("bu", "file_type.build", "build file (Makefile, Cargo.toml ..)"),
+    ("sc", "file_type.source", "source code"),
+];
+
+/// shorthands of EZA_COLORS that set several keys at once
+const SHORTHANDS: [(&str, [&str; 5]); 2] = [
+    ("sn", ["nb", "nk", "nm", "ng", "nt"]),
+    ("sb", ["ub", "uk", "um", "ug", "ut"]),
+];
+
+fn code_of(key: &str) -> Option<&str> {
+    let code = key.strip_prefix(PREFIX)?;
+    KEYS.iter().any(|(c, _, _)| *c == code).then_some(code)
+}
+
+/// true for 'eza:ur', 'eza:da' ..
+pub fn is_eza_key(key: &str) -> bool {
+    code_of(key).is_some()
+}
+
+pub fn description(key: &str) -> Option<String> {
+    let code = code_of(key)?;
+    KEYS.iter().find(|(c, _, _)| *c == code).map(|(_, _, description)| format!("eza: {}", description))
+}
+
+/// the EZA_COLORS string from a file or variable ('export EZA_COLORS="..."', 'EXA_COLORS=..' or the bare string)
+fn strip_assignment(content: &str) -> &str {
+    let content = content.trim();
+    let content = content.strip_prefix("export ").unwrap_or(content);
+    let content = content.strip_prefix("EZA_COLORS=").or_else(|| content.strip_prefix("EXA_COLORS=")).unwrap_or(content);
+    content.trim_matches(|c| c == '"' || c == '\'')
+}
+
+/// sets the entries of an EZA_COLORS string on the map, returns the keys eza doesn't know
+pub fn apply_eza_colors(map: &mut ColorMap, content: &str) -> Vec<String> {
+    let mut unknown = vec![];
+    for entry in strip_assignment(content).split(':').filter(|e| !e.is_empty()) {
+        let Some((key, values)) = entry.split_once('=') else {
+            // 'reset' drops eza's own extension colors, the map has none
+            if entry != "reset" {
+                unknown.push(entry.to_string());
+            }
+            continue;
+        };
+        let (style, fg, bg) = decode_values(values);
+        if FILE_KINDS.iter().any(|(code, _)| *code == key) || key.starts_with('*') {
+            map.set_values(key, style, fg, bg);
+        } else if let Some((_, codes)) = SHORTHANDS.iter().find(|(code, _)| *code == key) {
+            for code in codes {
+                map.set_values(&format!("{}{}", PREFIX, code), style.clone(), fg.clone(), bg.clone());
+            }
+        } else if KEYS.iter().any(|(code, _, _)| *code == key) {
+            map.set_values(&format!("{}{}", PREFIX, key), style, fg, bg);
+        } else {
+            unknown.push(key.to_string());
+        }
+    }
+    unknown
+}
+
+/// true for a colon separated string using one of the eza-only codes
+pub fn looks_like_eza_colors(content: &str) -> bool {
+    let content = content.trim();
+    if content.contains("EZA_COLORS=") || content.contains("EXA_COLORS=") {
+        return true;
+    }
+    strip_assignment(content).split(':').filter_map(|e| e.split_once('=')).any(|(key, _)| {
+        ["ur", "uw", "ux", "gr", "gx", "da", "sn", "sb", "uu", "ga", "gm", "xx", "hd"].contains(&key)
+    })
+}
+
+fn has_values(item: &LsItem) -> bool {
+    item.style.is_some() || item.color.is_some() || item.bg.is_some()
+}
+
+/// the entries eza understands in declared order: shared file types, patterns and eza keys (without prefix)
+fn eza_entries(map: &ColorMap, warnings: &mut Vec<String>) -> Vec<(String, LsItem)> {
+    let flat = map.flatten(true);
+    let mut entries: Vec<(&String, &LsItem)> = flat.iter().filter(|(key, item)| !is_variable(key) && has_values(item)).collect();
+    entries.sort_by_key(|(_, item)| item.order);
+    let mut dropped = vec![];
+    let mut result = vec![];
+    for (key, item) in entries {
+        if let Some(code) = code_of(key) {
+            result.push((code.to_string(), item.clone()));
+        } else if key.starts_with('*') || FILE_KINDS.iter().any(|(code, _)| code == key) {
+            result.push((key.clone(), item.clone()));
+        } else {
+            dropped.push(key.as_str());
+        }
+    }
+    if !dropped.is_empty() {
+        warnings.push(format!("{} have no eza key and are dropped", dropped.join(", ")));
+    }
+    result
+}
+
+/// the EZA_COLORS string of the map and a warning for the entries eza doesn't read
+pub fn to_eza_colors(map: &ColorMap) -> (String, Vec<String>) {
+    let mut warnings = vec![];
+    let entries = eza_entries(map, &mut warnings);
+    let values: Vec<String> = entries.iter().map(|(key, item)| format!("{}={}", key, item)).collect();
+    (values.join(":"), warnings)
+}
+
+/// color as eza's theme.yml writes it (the names of nu-ansi-term, 0 - 255 or '#rrggbb')
+fn yaml_color(color: &Color) -> Value {
+    // nu-ansi-term calls bright black 'DarkGray' and bright white 'LightGray'
+    const NAMES: [&str; 16] = [
+        "Black", "Red", "Green", "Yellow", "Blue", "Purple", "Cyan", "White",
+        "DarkGray", "LightRed", "LightGreen", "LightYellow", "LightBlue", "LightPurple", "LightCyan", "LightGray",
+    ];
+    Value::String(match color {
+        Color::_Ansi(n) => n.to_string(),
+        Color::_RGB((r, g, b)) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        basic => NAMES[basic.index().unwrap() as usize].to_string(),
+    })
+}
+
+fn color_from_yaml(value: &Value) -> Result<Option<Color>, String> {
+    let name = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        _ => return Err(format!("invalid color: {:?}", value)),
+    };
+    let index = match name.as_str() {
+        "Default" => return Ok(None),
+        "Black" => 0,
+        "Red" => 1,
+        "Green" => 2,
+        "Yellow" => 3,
+        "Blue" => 4,
+        "Purple" | "Magenta" => 5,
+        "Cyan" => 6,
+        "White" => 7,
+        "DarkGray" => 8,
+        "LightRed" => 9,
+        "LightGreen" => 10,
+        "LightYellow" => 11,
+        "LightBlue" => 12,
+        "LightPurple" | "LightMagenta" => 13,
+        "LightCyan" => 14,
+        "LightGray" => 15,
+        hex if hex.starts_with('#') => {
+            let rgb = parse_hex(hex).ok_or(format!("invalid color: {}", hex))?;
+            return Ok(Some(Color::_RGB(rgb)));
+        },
+        number => number.parse::<u8>().map_err(|_| format!("unknown color: {}", number))?,
+    };
+    Ok(Some(Color::from_index(index)))
+}
+
+const STYLE_FLAGS: [(&str, Style); 5] = [
+    ("is_bold", Style::Bold),
+    ("is_underline", Style::Underscore),
+    ("is_blink", Style::Blink),
+    ("is_reverse", Style::Reverse),
+    ("is_hidden", Style::Concealed),
+];
+
+fn style_to_yaml(item: &LsItem) -> Mapping {
+    let mut style = Mapping::new();
+    if let Some(color) = &item.color {
+        style.insert("foreground".into(), yaml_color(color));
+    }
+    if let Some(bg) = &item.bg {
+        style.insert("background".into(), yaml_color(&bg.to_color()));
+    }
+    if let Some((flag, _)) = STYLE_FLAGS.iter().find(|(_, s)| item.style.as_ref() == Some(s)) {
+        style.insert(Value::from(*flag), Value::Bool(true));
+    }
+    style
+}
+
+/// the values of a theme.yml style, only the first of several attributes is kept
+fn style_from_yaml(path: &str, value: &Value, warnings: &mut Vec<String>) -> Result<Values, String> {
+    let style = value.as_mapping().ok_or(format!("{}: expected a style", path))?;
+    let color = |name: &str| style.get(name).map(color_from_yaml).transpose().map(Option::flatten).map_err(|e| format!("{}: {}", path, e));
+    let (fg, bg) = (color("foreground")?, color("background")?);
+    let attributes: Vec<&(&str, Style)> = STYLE_FLAGS.iter().filter(|(flag, _)| style.get(*flag).and_then(Value::as_bool) == Some(true)).collect();
+    if attributes.len() > 1 {
+        let names: Vec<&str> = attributes.iter().map(|(flag, _)| *flag).collect();
+        warnings.push(format!("{}: only {} of {} is kept", path, names[0], names.join(", ")));
+    }
+    Ok((attributes.first().map(|(_, s)| s.clone()), fg, bg.map(|c| Background::from_color(&c))))
+}
+
+/// puts a value at a dotted path ('perms.user_read') of a yaml document, creating the sections on the way
+pub fn insert_path(document: &mut Mapping, path: &str, value: Value) {
+    match path.split_once('.') {
+        Some((section, rest)) => {
+            let section = document.entry(section.into()).or_insert_with(|| Value::Mapping(Mapping::new()));
+            if let Value::Mapping(section) = section {
+                insert_path(section, rest, value);
+            }
+        },
+        None => {
+            document.insert(path.into(), value);
+        },
+    }
+}
+
+pub fn get_path<'a>(document: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(document, |value, name| value.get(name))
+}
+
+/// the map as eza theme.yml and a warning for everything it can't hold
+pub fn to_eza_theme(map: &ColorMap) -> Result<(String, Vec<String>), serde_yaml::Error> {
+    let mut warnings = vec![];
+    let mut theme = Mapping::new();
+    let mut extensions = Mapping::new();
+    let mut dropped = vec![];
+    for (key, item) in eza_entries(map, &mut warnings) {
+        let style = style_to_yaml(&item);
+        let path = FILE_KINDS.iter().map(|(code, path)| (*code, *path)).chain(KEYS.iter().map(|(code, path, _)| (*code, *path)))
+            .find(|(code, _)| *code == key).map(|(_, path)| path);
+        match (path, key.strip_prefix("*.")) {
+            (Some(path), _) => insert_path(&mut theme, path, Value::Mapping(style)),
+            // eza matches the part after the last dot
+            (None, Some(extension)) if !extension.contains(['.', '*', '?']) => {
+                let mut entry = Mapping::new();
+                entry.insert("filename".into(), Value::Mapping(style));
+                extensions.insert(extension.into(), Value::Mapping(entry));
+            },
+            _ => dropped.push(key),
+        }
+    }
+    if !extensions.is_empty() {
+        theme.insert("extensions".into(), Value::Mapping(extensions));
+    }
+    if !dropped.is_empty() {
+        warnings.push(format!("theme.yml only has single extensions, {} are dropped (EZA_COLORS can hold them)", dropped.join(", ")));
+    }
+    Ok((serde_yaml::to_string(&theme)?, warnings))
+}
+
+/// sets the styles of an eza theme.yml on the map, returns warnings for what is lost
+pub fn apply_eza_theme(map: &mut ColorMap, content: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let theme: Value = serde_yaml::from_str(content)?;
+    let mut warnings = vec![];
+    let keys = FILE_KINDS.iter().map(|(code, path)| (code.to_string(), *path))
+        .chain(KEYS.iter().map(|(code, path, _)| (format!("{}{}", PREFIX, code), *path)));
+    for (key, path) in keys {
+        if let Some(value) = get_path(&theme, path) {
+            let (style, fg, bg) = style_from_yaml(path, value, &mut warnings)?;
+            map.set_values(&key, style, fg, bg);
+        }
+    }
+    if let Some(extensions) = theme.get("extensions").and_then(Value::as_mapping) {
+        for (extension, entry) in extensions {
+            let (Some(extension), Some(style)) = (extension.as_str(), entry.get("filename")) else {
+                continue;
+            };
+            let path = format!("extensions.{}", extension);
+            let (style, fg, bg) = style_from_yaml(&path, style, &mut warnings)?;
+            map.set_values(&format!("*.{}", extension), style, fg, bg);
+        }
+    }
+    if let Some(filenames) = theme.get("filenames").and_then(Value::as_mapping).filter(|f| !f.is_empty()) {
+        let names: Vec<&str> = filenames.keys().filter_map(Value::as_str).collect();
+        warnings.push(format!("LS_COLORS has no exact file names, the filenames section is skipped ({})", names.join(", ")));
+    }
+    Ok(warnings)
+}
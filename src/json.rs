@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::schema::Schema;
+use crate::{decode_values, Background, Color, ColorMap, LsItem, Style};
+
+/// one entry of the map as written to / read from json
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsonEntry {
+    pub key: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub order: Option<usize>,
+    #[serde(default)]
+    pub style: Option<Style>,
+    #[serde(default)]
+    pub fg: Option<Color>,
+    #[serde(default)]
+    pub bg: Option<Background>,
+    /// the raw LS_COLORS value, only used on import when style, fg and bg are all missing
+    #[serde(default)]
+    pub sgr: String,
+    /// the style variable providing the values
+    #[serde(default)]
+    pub reference: Option<String>,
+}
+
+impl JsonEntry {
+    fn from_item(key: &str, item: &LsItem) -> Self {
+        JsonEntry {
+            key: key.to_string(),
+            description: Some(item.description.clone()),
+            order: Some(item.order),
+            style: item.style.clone(),
+            fg: item.color.clone(),
+            bg: item.bg.clone(),
+            sgr: item.to_string(),
+            reference: item.reference.clone(),
+        }
+    }
+}
+
+/// serializes the given entries (in the given order) as a json array
+pub fn to_json(entries: &[(&str, &LsItem)]) -> Result<String, serde_json::Error> {
+    let json_entries: Vec<JsonEntry> = entries.iter().map(|(key, item)| JsonEntry::from_item(key, item)).collect();
+    serde_json::to_string_pretty(&json_entries)
+}
+
+/// serializes the whole map in declared order, with the values of referenced variables filled in
+pub fn map_to_json(map: &ColorMap) -> Result<String, serde_json::Error> {
+    let map = map.flatten(true);
+    let mut entries: Vec<(&str, &LsItem)> = map.iter().map(|(key, item)| (key.as_str(), item)).collect();
+    entries.sort_by_key(|(_, item)| item.order);
+    to_json(&entries)
+}
+
+/// builds a map for the variable from a json array as written by `export --format json`,
+/// `start` is ColorMap::new for a full map or ColorMap::empty_for for only the entries of the array
+pub fn map_from_json(json: &str, schema: &'static Schema, start: fn(&'static Schema) -> ColorMap) -> Result<ColorMap, Box<dyn std::error::Error>> {
+    let json_entries: Vec<JsonEntry> = serde_json::from_str(json)?;
+    let mut map = start(schema);
+    for entry in &json_entries {
+        if entry.style.is_none() && entry.fg.is_none() && entry.bg.is_none() {
+            let (style, fg, bg) = decode_values(&entry.sgr);
+            map.set_values(&entry.key, style, fg, bg);
+        } else {
+            map.set_values(&entry.key, entry.style.clone(), entry.fg.clone(), entry.bg.clone());
+        }
+        let item = map.get_mut(&entry.key).unwrap();
+        if let Some(description) = &entry.description {
+            item.description = description.clone();
+        }
+        if let Some(order) = entry.order {
+            item.order = order;
+        }
+    }
+    // variables can be declared after their users
+    for entry in &json_entries {
+        if let Some(variable) = &entry.reference {
+            map.set_reference(&entry.key, variable)?;
+        }
+    }
+    Ok(map)
+}
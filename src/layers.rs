@@ -0,0 +1,266 @@
+use std::{collections::HashSet, env, fs, path::Path};
+
+use crate::schema::{self, Schema};
+use crate::{dircolors, eza, json, lscolors, lsd, theme, ColorMap, LsItem};
+
+/// one source of entries, later layers take precedence over earlier ones
+#[derive(Debug)]
+pub struct Layer {
+    pub name: String,
+    /// where the entries came from (file path, $LS_COLORS ..)
+    pub source: String,
+    /// only the entries this layer sets
+    pub entries: ColorMap,
+}
+
+/// reads a file as toml theme, json, eza theme.yml, lsd colors.yaml, LS_COLORS, EZA_COLORS or BSD LSCOLORS string or dircolors database,
+/// only toml, json and the variable string for the other variables,
+/// `start` is ColorMap::new for a full map or ColorMap::empty_for for only the entries the file sets (also the empty ones)
+pub fn read_map_file(path: &Path, schema: &'static Schema, start: fn(&'static Schema) -> ColorMap) -> Result<ColorMap, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let map = match extension {
+        "toml" => theme::load_theme_with(path, start)?.0,
+        "json" => json::map_from_json(&content, schema, start)?,
+        _ if !schema.is_ls() => {
+            let mut map = start(schema);
+            map.parse_env_string(strip_variable(&content, schema).to_string());
+            map
+        },
+        "yml" | "yaml" => {
+            let mut map = start(schema);
+            if lsd::is_lsd_colors(&content) {
+                lsd::apply_lsd_colors(&mut map, &content)?;
+            } else {
+                eza::apply_eza_theme(&mut map, &content)?;
+            }
+            map
+        },
+        _ => {
+            let ls_string = strip_variable(&content, schema);
+            let mut map = start(schema);
+            if lscolors::looks_like_lscolors(&content) {
+                lscolors::apply_lscolors(&mut map, &content)?;
+            } else if eza::looks_like_eza_colors(&content) {
+                eza::apply_eza_colors(&mut map, &content);
+            } else if !ls_string.contains(char::is_whitespace) && ls_string.contains('=') {
+                map.parse_env_string(ls_string.to_string());
+            } else {
+                dircolors::apply_dircolors(&mut map, &content);
+            }
+            map
+        }
+    };
+    if map.schema != schema {
+        return Err(format!("{} is a theme for {}, not {}", path.display(), map.schema.variable, schema.variable).into());
+    }
+    Ok(map)
+}
+
+/// the string of a file or variable ('export GREP_COLORS="..."', 'GREP_COLORS=..' or the bare string)
+pub fn strip_variable<'a>(content: &'a str, schema: &Schema) -> &'a str {
+    let content = content.trim();
+    let content = content.strip_prefix("export ").unwrap_or(content);
+    let content = content.strip_prefix(schema.variable).and_then(|c| c.strip_prefix('=')).unwrap_or(content);
+    content.trim_matches(|c| c == '"' || c == '\'')
+}
+
+impl Layer {
+    /// the colors the program uses on its own
+    pub fn builtin(schema: &'static Schema) -> Self {
+        let mut entries = ColorMap::empty_for(schema);
+        entries.parse_env_string(schema.builtin.to_string());
+        Layer { name: "defaults".to_string(), source: "built-in".to_string(), entries }
+    }
+
+    pub fn from_env(schema: &'static Schema) -> Option<Self> {
+        let colors = env::var(schema.variable).ok()?;
+        let mut entries = ColorMap::empty_for(schema);
+        entries.parse_env_string(colors);
+        Some(Layer { name: "env".to_string(), source: format!("${}", schema.variable), entries })
+    }
+
+    /// the eza keys of $EZA_COLORS (or the older $EXA_COLORS), its file types and patterns are left
+    /// to $LS_COLORS: they only apply to eza and would change what ls gets
+    pub fn from_eza_env() -> Option<Self> {
+        let (variable, colors) = ["EZA_COLORS", "EXA_COLORS"].iter().find_map(|v| env::var(v).ok().map(|c| (v, c)))?;
+        let mut entries = ColorMap::empty();
+        eza::apply_eza_colors(&mut entries, &colors);
+        entries.retain(|key, _| eza::is_eza_key(key));
+        Some(Layer { name: "eza".to_string(), source: format!("${}", variable), entries })
+    }
+
+    pub fn from_file(name: &str, path: &Path, schema: &'static Schema) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Layer {
+            name: name.to_string(),
+            source: path.display().to_string(),
+            // an empty entry in the file clears the value of the layers below ('di=')
+            entries: read_map_file(path, schema, ColorMap::empty_for)?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct LayerStack {
+    pub layers: Vec<Layer>,
+    pub schema: &'static Schema,
+}
+
+impl Default for LayerStack {
+    fn default() -> Self {
+        LayerStack { layers: vec![], schema: &schema::LS_COLORS }
+    }
+}
+
+/// puts the entry on top of the map, keeping description and order of existing entries
+fn apply_entry(map: &mut ColorMap, key: &str, item: &LsItem) {
+    match map.get_mut(key) {
+        Some(existing) => {
+            existing.style = item.style.clone();
+            existing.color = item.color.clone();
+            existing.bg = item.bg.clone();
+            existing.reference = item.reference.clone();
+        },
+        None => {
+            let mut item = item.clone();
+            item.order = map.len() + 1;
+            map.insert(key.to_string(), item);
+        }
+    }
+}
+
+impl LayerStack {
+    /// the defaults of the program and the environment variable
+    pub fn from_env(schema: &'static Schema) -> Self {
+        let mut stack = LayerStack { layers: vec![Layer::builtin(schema)], schema };
+        stack.layers.extend(Layer::from_env(schema));
+        stack
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|l| l.name == name)
+    }
+
+    /// the map built from the first `count` layers
+    pub fn compose_until(&self, count: usize) -> ColorMap {
+        let mut map = ColorMap::new(self.schema);
+        for layer in self.layers.iter().take(count) {
+            let mut entries: Vec<(&String, &LsItem)> = layer.entries.iter().collect();
+            entries.sort_by_key(|(_, item)| item.order);
+            for (key, item) in entries {
+                apply_entry(&mut map, key, item);
+            }
+        }
+        map
+    }
+
+    pub fn compose(&self) -> ColorMap {
+        self.compose_until(self.layers.len())
+    }
+
+    /// name of the layer the current value of the entry comes from, 'session' for changes made at the prompt
+    pub fn origin(&self, composed: &ColorMap, map: &ColorMap, key: &str) -> String {
+        let current = map.get(key);
+        if current.is_some() && current.zip(composed.get(key)).is_none_or(|(a, b)| !a.same_values(b)) {
+            return "session".to_string();
+        }
+        match self.layers.iter().rev().find(|l| l.entries.contains_key(key)) {
+            Some(layer) => layer.name.clone(),
+            None => "-".to_string(),
+        }
+    }
+
+    /// keys of `map` whose value comes from the defaults of the program or from no layer at all
+    pub fn default_keys(&self, map: &ColorMap) -> HashSet<String> {
+        let composed = self.compose();
+        map.keys().filter(|key| matches!(self.origin(&composed, map, key).as_str(), "defaults" | "-")).cloned().collect()
+    }
+
+    /// the entries of `map` that differ from the stack up to (and including) the given layer
+    pub fn delta(&self, map: &ColorMap, layer: &str) -> Result<ColorMap, String> {
+        let position = self.position(layer).ok_or(format!("unknown layer: {}", layer))?;
+        let base = self.compose_until(position + 1);
+        let mut delta = ColorMap::empty_for(self.schema);
+        for (key, item) in map.iter() {
+            if base.get(key).is_none_or(|b| !b.same_values(item)) {
+                delta.insert(key.clone(), item.clone());
+            }
+        }
+        // variables are part of the delta as soon as one of the entries uses them
+        let used: Vec<String> = delta.values().filter_map(|item| item.reference.clone()).collect();
+        for variable in used {
+            if let Some(item) = map.get(&variable) {
+                delta.insert(variable, item.clone());
+            }
+        }
+        Ok(delta)
+    }
+
+    /// changes the stack with `change` and returns the new composition,
+    /// the changes made at the prompt are kept on top
+    pub fn rebuild<F>(&mut self, map: &ColorMap, change: F) -> Result<ColorMap, Box<dyn std::error::Error>>
+    where F: FnOnce(&mut Vec<Layer>) -> Result<(), Box<dyn std::error::Error>> {
+        let (session, removed) = self.session_changes(map);
+        change(&mut self.layers)?;
+        let mut composed = self.compose();
+        let mut changes: Vec<(&String, &LsItem)> = session.iter().collect();
+        changes.sort_by_key(|(_, item)| item.order);
+        for (key, item) in changes {
+            apply_entry(&mut composed, key, item);
+        }
+        for key in removed {
+            composed.remove(&key);
+        }
+        Ok(composed)
+    }
+
+    /// the entries changed at the prompt and the keys removed there
+    fn session_changes(&self, map: &ColorMap) -> (ColorMap, Vec<String>) {
+        let composed = self.compose();
+        let mut changes = ColorMap::empty_for(self.schema);
+        for (key, item) in map.iter() {
+            if composed.get(key).is_none_or(|c| !c.same_values(item)) {
+                changes.insert(key.clone(), item.clone());
+            }
+        }
+        let removed = composed.keys().filter(|key| !map.contains_key(*key)).cloned().collect();
+        (changes, removed)
+    }
+
+    pub fn print(&self, map: &ColorMap) {
+        for (i, layer) in self.layers.iter().enumerate() {
+            println!("{}: {:<10} {:<30} ({} entries)", i, layer.name, layer.source, layer.entries.len());
+        }
+        let composed = self.compose();
+        let flat = map.flatten(true);
+        let mut entries: Vec<(&String, &LsItem)> = flat.iter().collect();
+        entries.sort_by_key(|(_, item)| item.order);
+        for (key, item) in entries {
+            let origin = self.origin(&composed, map, key);
+            println!("{:<10} {:<12} : {}", origin, key, item.color_helper(&item.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the stack of GREP_COLORS set to `colors`
+    fn grep_stack(colors: &str) -> LayerStack {
+        let mut entries = ColorMap::empty_for(&schema::GREP_COLORS);
+        entries.parse_env_string(colors.to_string());
+        let env = Layer { name: "env".to_string(), source: "$GREP_COLORS".to_string(), entries };
+        LayerStack { layers: vec![Layer::builtin(&schema::GREP_COLORS), env], schema: &schema::GREP_COLORS }
+    }
+
+    #[test]
+    fn mt_sets_the_matches_over_the_defaults() {
+        let exported = crate::create_ls_string(&grep_stack("mt=01;32").compose());
+        assert!(exported.contains("ms=01;32") && exported.contains("mc=01;32"), "{}", exported);
+        assert!(!exported.contains("mt="), "{}", exported);
+        let again = grep_stack(&exported).compose();
+        assert_eq!(again["ms"].to_string(), "01;32");
+        assert_eq!(again["mc"].to_string(), "01;32");
+    }
+}
@@ -0,0 +1,102 @@
+use std::env;
+
+use crate::view::glob_match;
+use crate::ColorMap;
+
+/// the keys lf reads besides the file types and *.ext, a trailing '/' limits them to directories
+#[derive(Debug, PartialEq)]
+pub enum PathKey<'a> {
+    /// '~/Documents', '/tmp/*', '~/src/*/': matched against the full path, '*' stays within a component
+    Path { pattern: &'a str, dir_only: bool },
+    /// '.git/', '*.git/': matched against the name of directories
+    Dir(&'a str),
+}
+
+pub fn kind(key: &str) -> Option<PathKey<'_>> {
+    let (pattern, dir_only) = match key.strip_suffix('/') {
+        Some(pattern) if !pattern.is_empty() => (pattern, true),
+        _ => (key, false),
+    };
+    if pattern.starts_with('/') || pattern.starts_with('~') {
+        Some(PathKey::Path { pattern, dir_only })
+    } else if dir_only {
+        Some(PathKey::Dir(pattern))
+    } else {
+        None
+    }
+}
+
+pub fn is_path_key(key: &str) -> bool {
+    kind(key).is_some()
+}
+
+/// reason why lf never matches the key, Ok for valid keys
+pub fn validate(key: &str) -> Result<(), &'static str> {
+    match kind(key) {
+        Some(PathKey::Path { pattern, .. }) => {
+            if pattern.starts_with('~') && pattern != "~" && !pattern.starts_with("~/") {
+                Err("starts with ~name, lf only expands ~ and ~/")
+            } else if pattern.contains("//") {
+                Err("has an empty path component")
+            } else {
+                Ok(())
+            }
+        },
+        Some(PathKey::Dir(name)) if name.contains('/') => Err("is a relative path, lf matches the full path (start with / or ~/)"),
+        Some(PathKey::Dir(".." | ".")) => Err("is never the name of a listed directory"),
+        Some(PathKey::Dir(_)) => Ok(()),
+        None => Err("is neither a path (/.., ~/..) nor a directory name (name/)"),
+    }
+}
+
+pub fn description(key: &str) -> Option<String> {
+    let is_glob = |pattern: &str| pattern.contains(['*', '?']);
+    match kind(key)? {
+        PathKey::Path { pattern, dir_only: false } if is_glob(pattern) => Some(format!("lf: paths matching {}", pattern)),
+        PathKey::Path { pattern, dir_only: false } => Some(format!("lf: path {}", pattern)),
+        PathKey::Path { pattern, dir_only: true } => Some(format!("lf: directories matching {}", pattern)),
+        PathKey::Dir(name) if is_glob(name) => Some(format!("lf: directories named like {}", name)),
+        PathKey::Dir(name) => Some(format!("lf: directories named {}", name)),
+    }
+}
+
+/// '~' and '~/..' with $HOME, anything else as it is
+fn expand(path: &str) -> String {
+    match (path.strip_prefix('~'), env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home.trim_end_matches('/'), rest),
+        _ => path.to_string(),
+    }
+}
+
+/// glob per path component, like filepath.Match of go
+fn path_match(pattern: &str, path: &str) -> bool {
+    let patterns: Vec<&str> = pattern.split('/').collect();
+    let components: Vec<&str> = path.split('/').collect();
+    patterns.len() == components.len() && patterns.iter().zip(&components).all(|(p, c)| glob_match(p, c))
+}
+
+/// true when lf colors `path` with the key, the path is absolute (or starts with ~) and without trailing '/'
+pub fn matches(key: &str, path: &str, is_dir: bool) -> bool {
+    match kind(key) {
+        Some(PathKey::Path { pattern, dir_only }) => (is_dir || !dir_only) && path_match(&expand(pattern), &expand(path)),
+        Some(PathKey::Dir(name)) => is_dir && glob_match(name, path.rsplit('/').next().unwrap_or(path)),
+        None => false,
+    }
+}
+
+/// the entry lf uses for `path` ('~/src/', a trailing '/' marks a directory): paths, directory names,
+/// then di or the patterns and fi, the last matching entry of a kind wins
+pub fn resolve<'a>(map: &'a ColorMap, path: &str) -> Option<&'a str> {
+    let is_dir = path.len() > 1 && path.ends_with('/');
+    let path = if is_dir { path.trim_end_matches('/') } else { path };
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort_by_key(|key| std::cmp::Reverse(map[*key].order));
+
+    let last_path = keys.iter().find(|key| matches!(kind(key), Some(PathKey::Path { .. })) && matches(key, path, is_dir));
+    let last_dir = keys.iter().find(|key| matches!(kind(key), Some(PathKey::Dir(_))) && matches(key, path, is_dir));
+    let last_pattern = keys.iter().find(|key| !is_dir && key.starts_with('*') && !is_path_key(key) && glob_match(key, name));
+    let file_type = if is_dir { "di" } else { "fi" };
+    last_path.or(last_dir).or(last_pattern).map(|key| key.as_str())
+        .or(map.get_key_value(file_type).map(|(key, _)| key.as_str()))
+}
@@ -0,0 +1,210 @@
+use std::{collections::{HashMap, HashSet}, fmt};
+
+use crate::schema::Schema;
+use crate::{create_ls_string, is_variable, ColorMap, LsItem, Style};
+use crate::palette::{self, Palette, TermBackground};
+use crate::theme::ThemeInfo;
+use crate::{categories, cvd, lf};
+use crate::lscolors::shortened;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        };
+        f.pad(s)
+    }
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    /// stable id of the rule (ex.: 'invisible')
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub keys: Vec<String>,
+    pub message: String,
+    pub suggestion: String,
+}
+
+#[derive(Debug)]
+pub struct LintOptions {
+    /// maximum length of the exported LS_COLORS string
+    pub max_length: usize,
+    /// minimum contrast ratio between text and background
+    pub min_contrast: f64,
+    pub background: TermBackground,
+    /// terminal colors used to compute the contrast
+    pub palette: Palette,
+    /// keys the user never set, findings about them alone are left out
+    pub defaults: HashSet<String>,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        LintOptions { max_length: 4096, min_contrast: 4.5, background: TermBackground::Both, palette: Palette::default(), defaults: HashSet::new() }
+    }
+}
+
+fn finding(rule: &'static str, severity: Severity, keys: &[&str], message: String, suggestion: &str) -> Finding {
+    Finding { rule, severity, keys: keys.iter().map(|k| k.to_string()).collect(), message, suggestion: suggestion.to_string() }
+}
+
+fn has_values(item: &LsItem) -> bool {
+    item.style.is_some() || item.color.is_some() || item.bg.is_some()
+}
+
+/// the keys the program knows besides the patterns
+fn is_file_type(key: &str, schema: &Schema) -> bool {
+    schema.has_key(key) || (schema.is_ls() && ["lc", "rc", "ec", "cl"].contains(&key))
+}
+
+/// reason why a key can never match a file, None for valid keys
+fn never_matches(key: &str, schema: &Schema) -> Option<&'static str> {
+    if is_variable(key) || is_file_type(key, schema) {
+        return None;
+    }
+    if !schema.patterns {
+        return Some("is not a key of the variable");
+    }
+    if schema.paths && lf::is_path_key(key) {
+        return lf::validate(key).err();
+    }
+    if !key.starts_with('*') {
+        return Some("is neither a file type nor a pattern (*.ext)");
+    }
+    if key.contains('/') {
+        return Some("contains '/', file names never do");
+    }
+    if key == "*." || key == "*" {
+        return Some("has an empty suffix");
+    }
+    None
+}
+
+pub fn lint(map: &ColorMap, info: &ThemeInfo, options: &LintOptions) -> Vec<Finding> {
+    let flat = map.flatten(false);
+    let mut entries: Vec<(&String, &LsItem)> = flat.iter().collect();
+    entries.sort_by_key(|(_, item)| item.order);
+    let mut findings = vec![];
+
+    for (key, item) in &entries {
+        let invisible = match (&item.color, &item.bg) {
+            (Some(fg), Some(bg)) => options.palette.rgb(fg) == options.palette.bg_rgb(bg),
+            _ => false,
+        };
+        if invisible {
+            findings.push(finding("invisible", Severity::Error, &[key], format!("{} uses the same color for text and background", key), "change the color or the background"));
+        } else if item.color.is_some() && item.bg.is_some() {
+            // the terminal background doesn't show
+            if let Some(ratio) = palette::entry_contrast(&options.palette, item, TermBackground::Dark).filter(|r| *r < options.min_contrast) {
+                findings.push(finding("low-contrast", Severity::Warning, &[key], format!("{} has a contrast of {:.2}:1 (minimum {})", key, ratio, options.min_contrast), "pick colors further apart in brightness"));
+            }
+        } else {
+            for background in options.background.variants() {
+                if let Some(ratio) = palette::entry_contrast(&options.palette, item, background).filter(|r| *r < options.min_contrast) {
+                    findings.push(finding("low-contrast", Severity::Warning, &[key], format!("{} has a contrast of {:.2}:1 on a {} background (minimum {})", key, ratio, background.name(), options.min_contrast), "pick a color further from the background in brightness"));
+                }
+            }
+        }
+        match item.style {
+            Some(Style::Concealed) => findings.push(finding("concealed", Severity::Error, &[key], format!("{} hides the file name (08)", key), "use another style (ex.: 01 bold)")),
+            Some(Style::Blink) => findings.push(finding("blink", Severity::Warning, &[key], format!("{} blinks (05), most terminals ignore it or it distracts", key), "use bold (01) or underscore (04)")),
+            _ => (),
+        }
+        if let Some(reason) = never_matches(key, map.schema) {
+            findings.push(finding("never-matches", Severity::Warning, &[key], format!("{} {}", key, reason), "remove the entry or fix the key"));
+        }
+    }
+
+    let mut by_lowercase: HashMap<String, Vec<&str>> = HashMap::new();
+    for (key, _) in &entries {
+        if key.starts_with('*') {
+            by_lowercase.entry(key.to_lowercase()).or_default().push(key);
+        }
+    }
+    let mut duplicates: Vec<&Vec<&str>> = by_lowercase.values().filter(|keys| keys.len() > 1).collect();
+    duplicates.sort();
+    for keys in duplicates {
+        findings.push(finding("case-duplicate", Severity::Warning, keys, format!("{} differ only by case, ls may match either one", keys.join(", ")), "keep a single entry"));
+    }
+
+    for type_key in ["di", "ex"] {
+        let Some(type_item) = flat.get(type_key).filter(|item| has_values(item)) else {
+            continue;
+        };
+        let same: Vec<&str> = entries.iter().filter(|(key, item)| key.starts_with('*') && item.same_values(type_item)).map(|(key, _)| key.as_str()).collect();
+        if !same.is_empty() {
+            let what = if type_key == "di" { "directories" } else { "executables" };
+            findings.push(finding("looks-like-type", Severity::Warning, &same, format!("{} look like {} ({})", same.join(", "), what, type_key), "give the extensions a different color"));
+        }
+    }
+
+    let mut builtin = ColorMap::empty_for(map.schema);
+    builtin.parse_env_string(map.schema.builtin.to_string());
+    for (key, item) in &entries {
+        if builtin.get(key.as_str()).is_some_and(has_values) && !has_values(item) && !map.schema.is_flag(key) {
+            findings.push(finding("empty-override", Severity::Warning, &[key], format!("{} is empty and turns off the color {} uses on its own ({})", key, map.schema.program, builtin[key.as_str()]), "remove the entry to keep the default or set a color"));
+        }
+    }
+
+    let mut variables: Vec<(&String, &LsItem)> = map.iter().filter(|(key, _)| is_variable(key)).collect();
+    variables.sort_by_key(|(_, item)| item.order);
+    for (key, _) in variables {
+        if map.users_of(key).is_empty() {
+            findings.push(finding("unused-variable", Severity::Info, &[key], format!("{} is not used by any entry", key), "remove the variable"));
+        }
+    }
+
+    let categories = categories::all(info);
+    for deficiency in cvd::ALL {
+        // total color blindness is rare, the finding is informative
+        let severity = if deficiency == cvd::Deficiency::Achromatopsia { Severity::Info } else { Severity::Warning };
+        for (a, b) in cvd::confusable_categories(map, &categories, &options.palette, deficiency) {
+            let members: Vec<&str> = categories[&a].iter().chain(&categories[&b]).filter(|key| flat.contains_key(*key)).map(String::as_str).collect();
+            findings.push(finding("cvd-confusable", severity, &members, format!("categories {} and {} look alike with {}", a, b, deficiency.name()), "change the brightness of one of them or give it a style (bold, underscore)"));
+        }
+    }
+
+    let length = create_ls_string(map).len();
+    if length > options.max_length {
+        // the longest entries are the first candidates to shorten
+        let mut by_length: Vec<(&str, usize)> = entries.iter().map(|(key, item)| (key.as_str(), key.len() + 1 + item.to_string().len())).collect();
+        by_length.sort_by_key(|(_, length)| std::cmp::Reverse(*length));
+        let longest: Vec<&str> = by_length.iter().take(5).map(|(key, _)| *key).collect();
+        findings.push(finding("too-long", Severity::Warning, &longest, format!("{} is {} characters long (limit {}), the longest entries come first", map.schema.variable, length, options.max_length), "use 'export --minify' or drop rarely used extensions"));
+    }
+    // the length is the one of the whole string, the defaults included
+    findings.retain(|f| f.rule == "too-long" || !f.keys.iter().all(|key| options.defaults.contains(key)));
+    findings
+}
+
+pub fn print_findings(findings: &[Finding]) {
+    for f in findings {
+        let keys: Vec<&str> = f.keys.iter().map(String::as_str).collect();
+        match keys.is_empty() {
+            true => println!("{:<7} [{}] {}", f.severity, f.rule, f.message),
+            false => println!("{:<7} [{}] {} (keys: {})", f.severity, f.rule, f.message, shortened(&keys)),
+        }
+        println!("        fix: {}", f.suggestion);
+    }
+    let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+    println!("{} errors, {} warnings, {} infos", count(Severity::Error), count(Severity::Warning), count(Severity::Info));
+}
+
+/// 0 without findings (or only infos), 1 with warnings, 2 with errors
+pub fn exit_code(findings: &[Finding]) -> i32 {
+    match findings.iter().map(|f| f.severity).max() {
+        Some(Severity::Error) => 2,
+        Some(Severity::Warning) => 1,
+        _ => 0,
+    }
+}
@@ -0,0 +1,128 @@
+use crate::depth::nearest_16;
+use crate::palette::Palette;
+use crate::{describe, Background, Color, ColorMap, LsItem, Style};
+
+/// the entries of BSD/macOS LSCOLORS, one foreground and one background letter each
+pub const KEYS: [&str; 11] = ["di", "ln", "so", "pi", "ex", "bd", "cd", "su", "sg", "tw", "ow"];
+
+/// 'a' - 'h' for the 8 colors, uppercase is bold, 'x' is the default color
+fn decode_letter(letter: char) -> Result<(Option<u8>, bool), String> {
+    match letter {
+        'x' | 'X' => Ok((None, false)),
+        'a'..='h' => Ok((Some(letter as u8 - b'a'), false)),
+        'A'..='H' => Ok((Some(letter as u8 - b'A'), true)),
+        _ => Err(format!("invalid LSCOLORS letter: {} (a-h, A-H or x)", letter)),
+    }
+}
+
+/// the LSCOLORS string from a file or variable ('export LSCOLORS="exfx.."', 'LSCOLORS=exfx..' or the bare string)
+pub fn strip_assignment(content: &str) -> &str {
+    let content = content.trim();
+    let content = content.strip_prefix("export ").unwrap_or(content);
+    content.strip_prefix("LSCOLORS=").unwrap_or(content).trim_matches(|c| c == '"' || c == '\'')
+}
+
+/// sets the 11 entries of the LSCOLORS string on the map, an uppercase background letter is the normal color
+pub fn apply_lscolors(map: &mut ColorMap, content: &str) -> Result<(), String> {
+    let letters: Vec<char> = strip_assignment(content).chars().collect();
+    if letters.len() != 2 * KEYS.len() {
+        return Err(format!("LSCOLORS needs {} letters, found {}", 2 * KEYS.len(), letters.len()));
+    }
+    for (key, pair) in KEYS.iter().zip(letters.chunks(2)) {
+        let (fg, bold) = decode_letter(pair[0])?;
+        let (bg, _) = decode_letter(pair[1])?;
+        let style = if bold { Some(Style::Bold) } else { None };
+        let bg = bg.map(|n| Background::from_color(&Color::from_index(n)));
+        map.set_values(key, style, fg.map(Color::from_index), bg);
+    }
+    Ok(())
+}
+
+/// index of the color among the 16 (256 and rgb colors go to the nearest one) and a note when approximated
+fn basic_color(color: &Color, palette: &Palette) -> (u8, Option<String>) {
+    match color.index() {
+        Some(n) if n < 16 => (n, None),
+        _ => {
+            let n = nearest_16(palette.rgb(color), palette);
+            (n, Some(format!("{} is approximated as {}", color, crate::COLOR_NAMES[n as usize])))
+        },
+    }
+}
+
+fn letters(key: &str, item: &LsItem, palette: &Palette, warnings: &mut Vec<String>) -> String {
+    let bold = item.style == Some(Style::Bold);
+    if let Some(style) = item.style.as_ref().filter(|s| !matches!(s, Style::None | Style::Bold)) {
+        warnings.push(format!("{}: {} is dropped", key, describe::style_words(style)));
+    }
+    let fg_letter = match &item.color {
+        Some(color) => {
+            let (n, approximated) = basic_color(color, palette);
+            warnings.extend(approximated.map(|note| format!("{}: {}", key, note)));
+            // bold is the only way to get the bright colors with ls -G
+            if n >= 8 && !bold {
+                warnings.push(format!("{}: {} is written as bold {}", key, crate::COLOR_NAMES[n as usize], crate::COLOR_NAMES[n as usize - 8]));
+            }
+            if bold || n >= 8 { (b'A' + n % 8) as char } else { (b'a' + n) as char }
+        },
+        None => {
+            if bold {
+                warnings.push(format!("{}: bold without a color is dropped", key));
+            }
+            'x'
+        },
+    };
+    let bg_letter = match &item.bg {
+        Some(bg) => {
+            let (n, approximated) = basic_color(&bg.to_color(), palette);
+            warnings.extend(approximated.map(|note| format!("{}: background {}", key, note)));
+            if n >= 8 {
+                warnings.push(format!("{}: the background {} becomes {}", key, crate::COLOR_NAMES[n as usize], crate::COLOR_NAMES[n as usize - 8]));
+            }
+            (b'a' + n % 8) as char
+        },
+        None => 'x',
+    };
+    format!("{}{}", fg_letter, bg_letter)
+}
+
+/// anything but no values or a plain reset (00)
+fn is_colored(item: &LsItem) -> bool {
+    item.color.is_some() || item.bg.is_some() || item.style.as_ref().is_some_and(|s| *s != Style::None)
+}
+
+/// true for a 22 letter LSCOLORS string
+pub fn looks_like_lscolors(content: &str) -> bool {
+    let letters = strip_assignment(content);
+    letters.len() == 2 * KEYS.len() && letters.chars().all(|c| matches!(c, 'a'..='h' | 'A'..='H' | 'x' | 'X'))
+}
+
+/// the LSCOLORS string of the map and a warning for everything it can't hold
+pub fn to_lscolors(map: &ColorMap, palette: &Palette) -> (String, Vec<String>) {
+    let map = map.flatten(false);
+    let mut warnings = vec![];
+    let mut result = String::new();
+    for key in KEYS {
+        match map.get(key) {
+            Some(item) => result.push_str(&letters(key, item, palette, &mut warnings)),
+            None => result.push_str("xx"),
+        }
+    }
+
+    let mut skipped: Vec<(&String, &LsItem)> = map.iter().filter(|(key, item)| !KEYS.contains(&key.as_str()) && is_colored(item)).collect();
+    skipped.sort_by_key(|(_, item)| item.order);
+    let (patterns, types): (Vec<&str>, Vec<&str>) = skipped.iter().map(|(key, _)| key.as_str()).partition(|key| key.starts_with('*'));
+    if !types.is_empty() {
+        warnings.push(format!("{} have no LSCOLORS letters and are dropped", types.join(", ")));
+    }
+    if !patterns.is_empty() {
+        warnings.push(format!("LSCOLORS has no file extensions, {} patterns are dropped ({})", patterns.len(), shortened(&patterns)));
+    }
+    (result, warnings)
+}
+
+/// the first keys of a long list, ex.: '*.tar, *.zip and 40 more'
+pub fn shortened(keys: &[&str]) -> String {
+    let shown: Vec<&str> = keys.iter().take(8).copied().collect();
+    let more = if keys.len() > shown.len() { format!(" and {} more", keys.len() - shown.len()) } else { String::new() };
+    format!("{}{}", shown.join(", "), more)
+}
@@ -0,0 +1,115 @@
+use serde_yaml::{Mapping, Value};
+
+use crate::eza::{get_path, insert_path};
+use crate::lscolors::shortened;
+use crate::palette::parse_hex;
+use crate::{describe, Color, ColorMap, LsItem, Style};
+
+/// the file types of lsd's colors.yaml below 'file-type', lsd tells apart files owned by the user (uid)
+const FILE_TYPES: [(&str, &[&str]); 11] = [
+    ("ex", &["file.exec-uid", "file.exec-no-uid"]),
+    ("fi", &["file.uid-no-exec", "file.no-uid-no-exec"]),
+    ("di", &["dir.uid", "dir.no-uid"]),
+    ("pi", &["pipe"]),
+    ("ln", &["symlink.default"]),
+    ("or", &["symlink.broken"]),
+    ("mi", &["symlink.missing-target"]),
+    ("bd", &["block-device"]),
+    ("cd", &["char-device"]),
+    ("so", &["socket"]),
+    ("do", &["special"]),
+];
+
+/// crossterm names of the 16 colors in ansi order, the bright ones have the plain name
+const NAMES: [&str; 16] = [
+    "black", "dark_red", "dark_green", "dark_yellow", "dark_blue", "dark_magenta", "dark_cyan", "grey",
+    "dark_grey", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// basic colors by name, 256 colors as number and rgb as '#rrggbb'
+fn yaml_color(color: &Color) -> Value {
+    match color {
+        Color::_Ansi(n) => Value::from(*n),
+        Color::_RGB((r, g, b)) => Value::from(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+        basic => Value::from(NAMES[basic.index().unwrap() as usize]),
+    }
+}
+
+fn color_from_yaml(path: &str, value: &Value) -> Result<Option<Color>, String> {
+    match value {
+        Value::Number(n) => n.as_u64().filter(|n| *n < 256).map(|n| Some(Color::from_index(n as u8))).ok_or(format!("{}: invalid color: {}", path, n)),
+        Value::String(name) if name == "default" => Ok(None),
+        Value::String(name) if name.starts_with('#') => parse_hex(name).map(|rgb| Some(Color::_RGB(rgb))).ok_or(format!("{}: invalid color: {}", path, name)),
+        Value::String(name) => match NAMES.iter().position(|n| *n == name.to_lowercase().replace("gray", "grey")) {
+            Some(i) => Ok(Some(Color::from_index(i as u8))),
+            None => name.parse::<u8>().map(|n| Some(Color::from_index(n))).map_err(|_| format!("{}: unknown color: {}", path, name)),
+        },
+        _ => Err(format!("{}: invalid color", path)),
+    }
+}
+
+/// the file-type section of lsd's colors.yaml and a warning for everything it can't hold
+pub fn to_lsd_colors(map: &ColorMap) -> Result<(String, Vec<String>), serde_yaml::Error> {
+    let map = map.flatten(false);
+    let mut warnings = vec![];
+    let mut document = Mapping::new();
+    for (key, paths) in FILE_TYPES {
+        let Some(item) = map.get(key) else {
+            continue;
+        };
+        if let Some(style) = item.style.as_ref().filter(|s| **s != Style::None) {
+            warnings.push(format!("{}: {} is dropped, colors.yaml only has foreground colors", key, describe::style_words(style)));
+        }
+        if item.bg.is_some() {
+            warnings.push(format!("{}: the background is dropped", key));
+        }
+        // without a color lsd keeps its own
+        if let Some(color) = &item.color {
+            for path in paths {
+                insert_path(&mut document, &format!("file-type.{}", path), yaml_color(color));
+            }
+        }
+    }
+
+    let mut skipped: Vec<(&String, &LsItem)> = map.iter()
+        .filter(|(key, item)| !FILE_TYPES.iter().any(|(k, _)| k == key) && (item.color.is_some() || item.bg.is_some()))
+        .collect();
+    skipped.sort_by_key(|(_, item)| item.order);
+    let (patterns, types): (Vec<&str>, Vec<&str>) = skipped.iter().map(|(key, _)| key.as_str()).partition(|key| key.starts_with('*'));
+    if !types.is_empty() {
+        warnings.push(format!("{} have no lsd file type and are dropped", types.join(", ")));
+    }
+    if !patterns.is_empty() {
+        warnings.push(format!("colors.yaml has no file extensions, {} patterns are dropped ({})", patterns.len(), shortened(&patterns)));
+    }
+    Ok((serde_yaml::to_string(&document)?, warnings))
+}
+
+/// true for a yaml document with lsd's file-type section
+pub fn is_lsd_colors(content: &str) -> bool {
+    serde_yaml::from_str::<Value>(content).is_ok_and(|document| document.get("file-type").is_some())
+}
+
+/// sets the file types of lsd's colors.yaml on the map, returns warnings for what is lost
+pub fn apply_lsd_colors(map: &mut ColorMap, content: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let document: Value = serde_yaml::from_str(content)?;
+    let file_types = document.get("file-type").ok_or("no file-type section, not an lsd colors.yaml")?;
+    let mut warnings = vec![];
+    for (key, paths) in FILE_TYPES {
+        let mut colors = vec![];
+        for path in paths {
+            if let Some(value) = get_path(file_types, path) {
+                colors.push((*path, color_from_yaml(path, value)?));
+            }
+        }
+        let Some((first, color)) = colors.first().cloned() else {
+            continue;
+        };
+        if colors.iter().any(|(_, c)| *c != color) {
+            let all: Vec<&str> = colors.iter().map(|(path, _)| *path).collect();
+            warnings.push(format!("{}: LS_COLORS doesn't tell apart {}, {} is kept", key, all.join(" and "), first));
+        }
+        map.set_values(key, None, color, None);
+    }
+    Ok(warnings)
+}
@@ -2,92 +2,337 @@
 #![allow(unused_imports)]
 
 use std::str::FromStr;
-use std::{collections::HashMap, env, ops::Deref};
+use std::{collections::HashMap, env, ops::Deref, path::PathBuf};
 use std::io::{self, Write};
 
 use rustyline::error::ReadlineError;
 use rustyline::{DefaultEditor, Result};
 
+use clap::Parser;
+
 mod types;
+mod analyze;
+mod capability;
+mod categories;
 mod commands;
+mod compact;
+mod cvd;
+mod depth;
+mod describe;
+mod dircolors;
+mod diff;
+mod eza;
+mod json;
+mod layers;
+mod lf;
+mod lint;
+mod lscolors;
+mod lsd;
+mod merge;
+mod palette;
+mod query;
+mod schema;
+mod schemes;
+mod theme;
+mod view;
+mod vivid;
 //use commands::*;
+use commands::{CategoryAction, LintArgs, Repl, ReplCommand};
+use layers::{Layer, LayerStack};
+use palette::Palette;
+use theme::ThemeInfo;
 use types::*;
+use view::{print_preview_map, ViewArgs};
+
 
+/// interactive editor for LS_COLORS
+#[derive(Parser, Debug)]
+struct Cli {
+    /// base theme (toml, json, LS_COLORS string or dircolors) below the overlay
+    #[arg(long)]
+    base: Option<PathBuf>,
+    /// personal overrides on top of the base
+    #[arg(long)]
+    overlay: Option<PathBuf>,
+    /// color scheme of the terminal (alacritty, kitty, Xresources, foot, Windows Terminal or iTerm2)
+    #[arg(long)]
+    palette: Option<PathBuf>,
+    /// ask the terminal for its colors (cached), falls back to xterm colors
+    #[arg(long, conflicts_with = "palette")]
+    query_palette: bool,
+    /// colors in the preview (auto: only on a terminal and without NO_COLOR), export is not affected
+    #[arg(long, value_enum, default_value_t = capability::ColorChoice::Auto)]
+    color: capability::ColorChoice,
+    /// check the entries and exit (0: ok, 1: warnings, 2: errors)
+    #[arg(long)]
+    lint: bool,
+    #[command(flatten)]
+    lint_args: LintArgs,
+}
+
+/// a file given on the command line can't be used, the exit code is the one of lint errors
+fn exit_unreadable(file: &std::path::Path, error: impl std::fmt::Display) -> ! {
+    eprintln!("cannot read {}: {}", file.display(), error);
+    std::process::exit(2);
+}
 
 fn main() {
+    let cli = Cli::parse();
+    capability::init(cli.color);
 
-    let mut color_decs = ColorMap::default();
-    match env::var_os("LS_COLORS") {
-        Some(colors) => { 
-            let color_str = colors.into_string().unwrap();
-            color_decs.parse_env_string(color_str);
-            
-            
+    let mut layer_stack = LayerStack::default();
+    layer_stack.layers.push(Layer::builtin(&schema::LS_COLORS));
+    for (name, file) in [("base", &cli.base), ("overlay", &cli.overlay)] {
+        if let Some(file) = file {
+            match Layer::from_file(name, file, &schema::LS_COLORS) {
+                Ok(layer) => layer_stack.layers.push(layer),
+                Err(e) => exit_unreadable(file, e),
+            }
+        }
+    }
+    match Layer::from_env(&schema::LS_COLORS) {
+        Some(layer) => layer_stack.layers.push(layer),
+        None if layer_stack.layers.len() == 1 => panic!("$LS_COLORS is not set"),
+        None => (),
+    };
+    if let Some(layer) = Layer::from_eza_env() {
+        layer_stack.layers.push(layer);
+    }
+    let mut color_decs = layer_stack.compose();
+    let mut initial_decs = color_decs.clone();
+    let mut palette = match &cli.palette {
+        Some(file) => schemes::import_scheme(file, None).unwrap_or_else(|e| exit_unreadable(file, e)),
+        None if cli.query_palette => match query::live_palette(std::time::Duration::from_millis(500), false) {
+            Ok((palette, _)) => palette,
+            Err(e) => {
+                println!("cannot query the terminal ({}), using the xterm palette", e);
+                Palette::default()
+            },
         },
-        None => panic!("$LS_COLORS is not set"),
+        None => Palette::default(),
     };
 
+    let mut theme_info = ThemeInfo::default();
+    // the sessions of the variables that are not edited at the moment
+    let mut stashed: HashMap<&str, (ColorMap, ColorMap, LayerStack, ThemeInfo)> = HashMap::new();
+
+    if cli.lint {
+        let findings = lint::lint(&color_decs, &theme_info, &cli.lint_args.options(&palette, layer_stack.default_keys(&color_decs)));
+        lint::print_findings(&findings);
+        std::process::exit(lint::exit_code(&findings));
+    }
+
     //start_loop().unwrap();
     let mut rl = DefaultEditor::new().unwrap();
     loop {
-       let buffer = rl.readline(">> ").unwrap();
+        let buffer = rl.readline(">> ").unwrap();
         let _ = rl.add_history_entry(buffer.as_str());
-        let commands:Vec<_> = buffer.split(' ').collect();
-        match *commands.as_slice() {
-            [single_cmd] => {
-                match single_cmd {
-                    "help" => {
-                        commands::print_help();
-                    }
-                    "quit" => {break;},
-                    "view" => {
-                        print_preview_map(&color_decs);
-        
+        let Some(words) = shlex::split(&buffer) else {
+            println!("unknown instruction: {}", buffer);
+            continue;
+        };
+        if words.is_empty() {
+            continue;
+        }
+        let repl = match Repl::try_parse_from(words) {
+            Ok(repl) => repl,
+            Err(e) => {
+                let _ = e.print();
+                continue;
+            }
+        };
+        match repl.command {
+            ReplCommand::Help => {
+                commands::print_help();
+            },
+            ReplCommand::Quit => {break;},
+            ReplCommand::View(args) => {
+                print_preview_map(&color_decs, &theme_info, &palette, &args);
+            },
+            ReplCommand::Export(args) => {
+                let result = match &args.delta {
+                    Some(layer) => {
+                        // without a base layer the delta is taken against the defaults
+                        let layer = if layer == "base" && layer_stack.position("base").is_none() { "defaults" } else { layer };
+                        layer_stack.delta(&color_decs, layer).map_err(|e| e.into())
+                            .and_then(|delta| commands::export_action(&delta, &theme_info, &palette, &args))
                     },
-                    "export" => {
-                        let ls_str = create_ls_string(&color_decs);
-                        println!("LS_COLORS=\"{}\"", ls_str);
+                    None => commands::export_action(&color_decs, &theme_info, &palette, &args),
+                };
+                if let Err(e) = result {
+                    println!("{}", e);
+                }
+            },
+            ReplCommand::Diff { a, b, against_defaults } => {
+                if let Err(e) = commands::diff_action(&color_decs, &initial_decs, a.as_deref(), b.as_deref(), against_defaults) {
+                    println!("{}", e);
+                }
+            },
+            ReplCommand::Merge { base, ours, theirs, interactive, output, format } => {
+                match commands::merge_action(&mut color_decs, &initial_decs, &theme_info, [&base, &ours, &theirs], interactive, output.as_deref(), format) {
+                    Ok(_) => {
+                        println!("Success merge action.");
+                        if output.is_none() {
+                            print_preview_map(&color_decs, &theme_info, &palette, &ViewArgs::default());
+                        }
                     },
-                    unknown => println!("Unknown single command: {}", unknown)
+                    Err(e) => println!("{}", e),
                 }
             },
-            [action, which, what, value] => {
-                if action == "set" {
-                    match commands::set_action(&mut color_decs, which, what, value) {
+            ReplCommand::Lint(args) => {
+                let findings = lint::lint(&color_decs, &theme_info, &args.options(&palette, layer_stack.default_keys(&color_decs)));
+                lint::print_findings(&findings);
+            },
+            ReplCommand::Palette { action } => {
+                match action {
+                    Some(action) => match commands::palette_action(&mut palette, action) {
                         Ok(_) => {
-                            println!("Success set action.");
-                            print_preview_map(&color_decs);
+                            println!("Success palette action.");
+                            palette.print();
                         },
                         Err(e) => println!("{}", e),
-                    }
+                    },
+                    None => palette.print(),
                 }
-
-                if action == "add" {
-                    match commands::add_action(&mut color_decs, which, what, value) {
-                        Ok(_) => {
-                            println!("Success add action.");
-                            print_preview_map(&color_decs);
-                        },
-                        Err(e) => println!("{}", e),
-                    }
+            },
+            ReplCommand::Analyze { threshold, background } => {
+                if let Err(e) = commands::analyze_action(&color_decs, &theme_info, &palette, threshold, background) {
+                    println!("{}", e);
                 }
             },
-            [action, which] => {
-                if action == "remove" {
-                    match commands::remove_action(&mut color_decs, which) {
+            ReplCommand::Variable { name: None } => {
+                let others: Vec<&str> = schema::ALL.iter().map(|s| s.variable).filter(|v| *v != color_decs.schema.variable).collect();
+                println!("editing {} ({}), others: {}", color_decs.schema.variable, color_decs.schema.program, others.join(", "));
+            },
+            ReplCommand::Variable { name: Some(name) } => {
+                let Some(schema) = schema::find(&name) else {
+                    println!("unknown variable: {}", name);
+                    continue;
+                };
+                // every variable keeps its own session, the first switch starts from the defaults and the environment
+                let (map, initial, stack, info) = stashed.remove(schema.variable).unwrap_or_else(|| {
+                    let stack = LayerStack::from_env(schema);
+                    let map = stack.compose();
+                    (map.clone(), map, stack, ThemeInfo::default())
+                });
+                let previous = (
+                    std::mem::replace(&mut color_decs, map),
+                    std::mem::replace(&mut initial_decs, initial),
+                    std::mem::replace(&mut layer_stack, stack),
+                    std::mem::replace(&mut theme_info, info),
+                );
+                stashed.insert(previous.0.schema.variable, previous);
+                println!("editing {} ({})", schema.variable, schema.program);
+                print_preview_map(&color_decs, &theme_info, &palette, &ViewArgs::default());
+            },
+            ReplCommand::Layers { action } => {
+                match action {
+                    Some(action) => match commands::layers_action(&mut color_decs, &mut layer_stack, action) {
                         Ok(_) => {
-                            println!("Success remove action.");
-                            print_preview_map(&color_decs);
+                            println!("Success layers action.");
+                            layer_stack.print(&color_decs);
                         },
                         Err(e) => println!("{}", e),
-                    }
+                    },
+                    None => layer_stack.print(&color_decs),
                 }
-            }
-            //
-            
-            [..] => {
-                println!("unknown instruction: {}", buffer);
-            }
+            },
+            ReplCommand::Import { file, format, filetypes } => {
+                match commands::import_action(&mut color_decs, &mut theme_info, &file, format, filetypes.as_deref()) {
+                    Ok(_) => {
+                        println!("Success import action.");
+                        print_preview_map(&color_decs, &theme_info, &palette, &ViewArgs::default());
+                    },
+                    Err(e) => println!("{}", e),
+                }
+            },
+            ReplCommand::Save { file } => {
+                match theme::save_theme(&file, &color_decs, &theme_info) {
+                    Ok(_) => println!("Success save action."),
+                    Err(e) => println!("{}", e),
+                }
+            },
+            ReplCommand::Load { file } => {
+                match theme::load_theme(&file) {
+                    Ok((map, _)) if map.schema != color_decs.schema => {
+                        println!("{} is a theme for {}, switch with 'variable {}'", file.display(), map.schema.variable, map.schema.variable);
+                    },
+                    Ok((map, info)) => {
+                        color_decs = map;
+                        theme_info = info;
+                        println!("Success load action.");
+                        print_preview_map(&color_decs, &theme_info, &palette, &ViewArgs::default());
+                    },
+                    Err(e) => println!("{}", e),
+                }
+            },
+            ReplCommand::Theme { name, author, description } => {
+                if let Some(name) = name {
+                    theme_info.name = name;
+                }
+                if let Some(author) = author {
+                    theme_info.author = author;
+                }
+                if let Some(description) = description {
+                    theme_info.description = description;
+                }
+                theme_info.print();
+            },
+            ReplCommand::Note { which, text } => {
+                match commands::note_action(&color_decs, &mut theme_info, &which, &text) {
+                    Ok(_) => println!("Success note action."),
+                    Err(e) => println!("{}", e),
+                }
+            },
+            ReplCommand::Category { action } => {
+                match commands::category_action(&color_decs, &mut theme_info, &action) {
+                    Ok(_) => {
+                        if !matches!(action, CategoryAction::List) {
+                            println!("Success category action.");
+                        }
+                    },
+                    Err(e) => println!("{}", e),
+                }
+            },
+            ReplCommand::Set { which, what, value } => {
+                let result = match which.strip_prefix("category:") {
+                    Some(name) => commands::set_category_action(&mut color_decs, &theme_info, name, &what, &value),
+                    None => commands::set_action(&mut color_decs, &which, &what, &value),
+                };
+                match result {
+                    Ok(_) => {
+                        println!("Success set action.");
+                        print_preview_map(&color_decs, &theme_info, &palette, &ViewArgs::default());
+                    },
+                    Err(e) => println!("{}", e),
+                }
+            },
+            ReplCommand::Add { which, what, value } => {
+                let result = match which.strip_prefix("category:") {
+                    Some(name) => commands::add_category_action(&mut color_decs, &theme_info, name, &what, &value),
+                    None => commands::add_action(&mut color_decs, &which, &what, &value),
+                };
+                match result {
+                    Ok(_) => {
+                        println!("Success add action.");
+                        print_preview_map(&color_decs, &theme_info, &palette, &ViewArgs::default());
+                    },
+                    Err(e) => println!("{}", e),
+                }
+            },
+            ReplCommand::Remove { which } => {
+                let result = match which.strip_prefix("category:") {
+                    Some(name) => commands::remove_category_action(&mut color_decs, &theme_info, name),
+                    None => commands::remove_action(&mut color_decs, &which),
+                };
+                match result {
+                    Ok(_) => {
+                        println!("Success remove action.");
+                        print_preview_map(&color_decs, &theme_info, &palette, &ViewArgs::default());
+                    },
+                    Err(e) => println!("{}", e),
+                }
+            },
         }
     }
     //print_preview_map(&color_decs);
@@ -97,63 +342,20 @@ fn main() {
     //do so
 }
 
-fn print_preview_map(color_decs: &ColorMap) {
-    let mut preview_map: HashMap<String, Vec<(&str, &LsItem)>> = HashMap::new();
-
-    for (key, item) in color_decs.deref() {
-        
-        //let k = item.make_color_id();
-        let mut k = item.to_string();
-        if !key.starts_with("*.") {
-            k.push_str(" - ");
-            k.push_str(key);
-            
-            preview_map.insert(k, vec![(key, &item)]);
-            
-        } else {
-            k.push_str(" - ext");
-            preview_map.entry(k).and_modify(|v| v.push((key, &item))).or_insert(vec![(key, &item)]);
-        }
-        //println!("{}: {}", key, item.preview());
-    }
-    // for (_color_id, item) in &preview_map {
-    //     let value: String;
-    //     if item.len() > 1 {
-    //         let d = item.iter().map(|e| e.0).collect::<Vec<_>>().join(";");
-    //         value = item[0].1.color_helper(d);
-    //     } else {
-    //         value = format!("[{}] {}", item[0].0, item[0].1._preview());
-    //     }
-    //     //println!("{} : {}", item[0].1.display_colors_values(), value);
-    //     //println!("{:<13}: {}", color_id, value);
-    // }
-    let mut map_vec: Vec<_> = preview_map.values().collect();
-    map_vec.sort_by_key(|items| items[0].1.order);
-    for item in map_vec {
-        let value: String;
-        if item.len() > 1 {
-            // let d = item.iter().map(|e| e.0).collect::<Vec<_>>().join(";");
-            // value = item[0].1.color_helper(d);
-            let files_vec = item.iter().map(|e| e.0).collect::<Vec<_>>();
-            let mut chunks = files_vec.chunks(13);
-            println!("{} : {}", item[0].1.display_colors_values(), item[0].1.color_helper(chunks.next().unwrap().join(";").as_ref()));
-            //let spacer = "                     ";
-            while let Some(c) = chunks.next() {
-                let line = c.join(";");
-                println!("{:>width$}", item[0].1.color_helper(&line), width=line.len() + 33);
-            }
-        } else {
-            value = format!("[{}] {}", item[0].0, item[0].1._preview());
-            println!("{} : {}", item[0].1.display_colors_values(), value);
-        }
-        
-        //println!("{}", value);
-    }
-}
-
 fn create_ls_string(color_decs: &ColorMap) -> String {
+    let color_decs = color_decs.flatten(false);
     let mut key_vec: Vec<(&String, &LsItem)> = color_decs.iter().collect();
     key_vec.sort_by_key(|&item| item.1.order);
-    let ls_values: Vec<String> = key_vec.iter().map(|&entry| format!("{}={}", entry.0, entry.1.to_string())).collect();
+    // a shorthand is left out once its members are there, written after them it would undo their changes
+    let schema = color_decs.schema;
+    key_vec.retain(|(key, _)| {
+        let members = schema.members(key);
+        members.is_empty() || !members.iter().all(|member| color_decs.contains_key(*member))
+    });
+    // flags are written by name when they are on
+    let ls_values: Vec<String> = key_vec.iter().filter_map(|entry| match color_decs.schema.is_flag(entry.0) {
+        true => color_decs.flag_is_on(entry.0).then(|| entry.0.to_string()),
+        false => Some(format!("{}={}", entry.0, entry.1)),
+    }).collect();
     ls_values.join(":")
 }
@@ -0,0 +1,223 @@
+use std::io::{self, Write};
+
+use crate::{diff, dircolors, eza, is_variable, theme, ColorMap, LsItem};
+use crate::theme::ThemeInfo;
+
+/// an entry changed differently on both sides, None stands for a removed entry
+#[derive(Debug)]
+pub struct Conflict {
+    pub key: String,
+    pub base: Option<LsItem>,
+    pub ours: Option<LsItem>,
+    pub theirs: Option<LsItem>,
+}
+
+/// entries referencing a variable are the same when they reference the same one, a change of the variable
+/// is a change of the variable entry only
+fn same(a: Option<&LsItem>, b: Option<&LsItem>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => match (&a.reference, &b.reference) {
+            (Some(ref_a), Some(ref_b)) => ref_a == ref_b,
+            (None, None) => a.same_look(b),
+            _ => false,
+        },
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// entries referencing a variable of the map take their look from it again, the ones whose variable
+/// is gone keep the look they had on their side
+fn settle_references(map: &mut ColorMap) {
+    let variables: Vec<String> = map.keys().filter(|key| is_variable(key)).cloned().collect();
+    for item in map.values_mut() {
+        match &item.reference {
+            Some(variable) if variables.contains(variable) => {
+                item.style = None;
+                item.color = None;
+                item.bg = None;
+            },
+            Some(_) => item.reference = None,
+            None => (),
+        }
+    }
+}
+
+/// three-way merge, the non-conflicting changes of both sides are applied,
+/// conflicting entries keep our side in the merged map;
+/// style variables and eza keys are merged like the other entries, references are kept
+pub fn merge_maps(base: &ColorMap, ours: &ColorMap, theirs: &ColorMap) -> (ColorMap, Vec<Conflict>) {
+    // the entries carry the look of their variable and the reference
+    let (base, ours, theirs) = (base.flatten(true), ours.flatten(true), theirs.flatten(true));
+    let mut merged = ours.clone();
+    let mut conflicts = vec![];
+
+    let mut keys: Vec<&String> = ours.keys().chain(theirs.keys()).chain(base.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let (b, o, t) = (base.get(key), ours.get(key), theirs.get(key));
+        if same(o, t) || same(b, t) {
+            continue;
+        }
+        if same(b, o) {
+            // only their side changed
+            match t {
+                Some(item) => set_entry(&mut merged, key, item),
+                None => { merged.remove(key); },
+            }
+        } else {
+            conflicts.push(Conflict { key: key.clone(), base: b.cloned(), ours: o.cloned(), theirs: t.cloned() });
+        }
+    }
+    conflicts.sort_by_key(|c| c.ours.as_ref().or(c.theirs.as_ref()).map(|item| item.order));
+    settle_references(&mut merged);
+    (merged, conflicts)
+}
+
+/// the look and the reference of `item`, `settle_references` sorts out the references afterwards
+fn set_entry(map: &mut ColorMap, key: &str, item: &LsItem) {
+    map.set_values(key, item.style.clone(), item.color.clone(), item.bg.clone());
+    if let Some(entry) = map.get_mut(key) {
+        entry.reference = item.reference.clone();
+    }
+}
+
+fn sample(item: Option<&LsItem>) -> String {
+    match item {
+        Some(item) => diff::sample(item),
+        None => "(removed)".to_string(),
+    }
+}
+
+/// asks for every conflict which side to take
+pub fn resolve_interactive(merged: &mut ColorMap, conflicts: &[Conflict]) -> io::Result<()> {
+    for conflict in conflicts {
+        println!("conflict: {}", conflict.key);
+        println!("  [b]ase  : {}", sample(conflict.base.as_ref()));
+        println!("  [o]urs  : {}", sample(conflict.ours.as_ref()));
+        println!("  [t]heirs: {}", sample(conflict.theirs.as_ref()));
+        let choice = loop {
+            print!("take [o/t/b]: ");
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            if io::stdin().read_line(&mut answer)? == 0 {
+                // end of input, keep our side for the rest
+                return Ok(());
+            }
+            match answer.trim() {
+                "o" | "ours" => break conflict.ours.as_ref(),
+                "t" | "theirs" => break conflict.theirs.as_ref(),
+                "b" | "base" => break conflict.base.as_ref(),
+                _ => println!("please answer o, t or b"),
+            }
+        };
+        match choice {
+            Some(item) => set_entry(merged, &conflict.key, item),
+            None => { merged.remove(&conflict.key); },
+        }
+        settle_references(merged);
+    }
+    Ok(())
+}
+
+fn marker_block(conflict: &Conflict, line: impl Fn(&str, &LsItem) -> Option<String>) -> String {
+    let side = |item: &Option<LsItem>| item.as_ref().and_then(|i| line(&conflict.key, i)).map(|l| l + "\n").unwrap_or_default();
+    format!("<<<<<<< ours\n{}=======\n{}>>>>>>> theirs", side(&conflict.ours), side(&conflict.theirs))
+}
+
+/// dircolors database of the merged map with conflict markers for the conflicting entries,
+/// dircolors has no style variables and eza keys, their conflicts only show in the looks of the entries
+pub fn dircolors_with_conflicts(merged: &ColorMap, conflicts: &[Conflict]) -> String {
+    let mut content = dircolors::to_dircolors(&without_conflicts(merged, conflicts));
+    for conflict in conflicts.iter().filter(|c| !is_variable(&c.key) && !eza::is_eza_key(&c.key)) {
+        content.push_str(&marker_block(conflict, dircolors::entry_line));
+        content.push('\n');
+    }
+    content
+}
+
+/// toml theme of the merged map, the conflicting lines of the [colors] table are replaced by conflict markers
+pub fn toml_with_conflicts(merged: &ColorMap, info: &ThemeInfo, conflicts: &[Conflict]) -> Result<String, toml::ser::Error> {
+    let mut with_placeholders = merged.clone();
+    for conflict in conflicts {
+        if !with_placeholders.contains_key(&conflict.key) {
+            set_entry(&mut with_placeholders, &conflict.key, conflict.theirs.as_ref().or(conflict.base.as_ref()).unwrap());
+        }
+    }
+    settle_references(&mut with_placeholders);
+    let content = theme::theme_to_string(&with_placeholders, info)?;
+    let mut blocks = vec![];
+    for conflict in conflicts {
+        let line = theme::colors_line(&conflict.key, &with_placeholders[&conflict.key])?;
+        blocks.push((line, marker_block(conflict, |key, item| theme::colors_line(key, item).ok())));
+    }
+    let lines: Vec<String> = content.lines().map(|line| {
+        match blocks.iter().find(|(l, _)| l == line) {
+            Some((_, block)) => block.clone(),
+            None => line.to_string(),
+        }
+    }).collect();
+    Ok(lines.join("\n") + "\n")
+}
+
+fn without_conflicts(merged: &ColorMap, conflicts: &[Conflict]) -> ColorMap {
+    let mut map = merged.clone();
+    for conflict in conflicts {
+        map.remove(&conflict.key);
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// di, *.tar using $arc and the eza key ur
+    fn base() -> ColorMap {
+        let mut map = ColorMap::default();
+        map.parse_env_string("di=01;34:$arc=01;31:*.tar=".to_string());
+        let (style, fg, bg) = crate::types::decode_values("33");
+        map.set_values("eza:ur", style, fg, bg);
+        map.set_reference("*.tar", "$arc").unwrap();
+        map
+    }
+
+    fn look(map: &ColorMap, key: &str) -> String {
+        map.resolve(&map[key]).to_string()
+    }
+
+    #[test]
+    fn keeps_variables_references_and_eza_keys() {
+        let mut theirs = base();
+        theirs.parse_env_string("di=01;35".to_string());
+        let (merged, conflicts) = merge_maps(&base(), &base(), &theirs);
+        assert!(conflicts.is_empty());
+        assert_eq!(look(&merged, "di"), "01;35");
+        assert_eq!(look(&merged, "$arc"), "01;31");
+        assert_eq!(merged["*.tar"].reference.as_deref(), Some("$arc"));
+        assert_eq!(look(&merged, "*.tar"), "01;31");
+        assert_eq!(look(&merged, "eza:ur"), "33");
+    }
+
+    #[test]
+    fn a_changed_variable_reaches_its_users() {
+        let mut theirs = base();
+        theirs.parse_env_string("$arc=32".to_string());
+        let (merged, conflicts) = merge_maps(&base(), &base(), &theirs);
+        assert!(conflicts.is_empty());
+        assert_eq!(merged["*.tar"].reference.as_deref(), Some("$arc"));
+        assert_eq!(look(&merged, "*.tar"), "32");
+    }
+
+    #[test]
+    fn users_of_a_removed_variable_keep_their_look() {
+        let mut theirs = base();
+        theirs.detach("*.tar");
+        theirs.remove("$arc");
+        let (merged, _) = merge_maps(&base(), &base(), &theirs);
+        assert!(!merged.contains_key("$arc"));
+        assert_eq!(merged["*.tar"].reference, None);
+        assert_eq!(look(&merged, "*.tar"), "01;31");
+    }
+}
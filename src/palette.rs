@@ -0,0 +1,247 @@
+use clap::ValueEnum;
+
+use crate::{ansi_256_rgb, capability, depth, Background, Color, LsItem, Style};
+use crate::depth::Depth;
+
+pub type Rgb = (u8, u8, u8);
+
+/// the colors a terminal uses for the 16 basic colors and its default text and background
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    pub ansi: [Rgb; 16],
+    pub foreground: Rgb,
+    pub background: Rgb,
+}
+
+pub const PRESETS: [&str; 3] = ["xterm", "vga", "tango"];
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::xterm()
+    }
+}
+
+impl Palette {
+    pub fn xterm() -> Self {
+        Palette {
+            name: "xterm".to_string(),
+            ansi: std::array::from_fn(|i| Color::from_index(i as u8).rgb()),
+            foreground: (229, 229, 229),
+            background: (0, 0, 0),
+        }
+    }
+
+    pub fn vga() -> Self {
+        Palette {
+            name: "vga".to_string(),
+            ansi: [
+                (0, 0, 0), (170, 0, 0), (0, 170, 0), (170, 85, 0), (0, 0, 170), (170, 0, 170), (0, 170, 170), (170, 170, 170),
+                (85, 85, 85), (255, 85, 85), (85, 255, 85), (255, 255, 85), (85, 85, 255), (255, 85, 255), (85, 255, 255), (255, 255, 255),
+            ],
+            foreground: (170, 170, 170),
+            background: (0, 0, 0),
+        }
+    }
+
+    pub fn tango() -> Self {
+        Palette {
+            name: "tango".to_string(),
+            ansi: [
+                (46, 52, 54), (204, 0, 0), (78, 154, 6), (196, 160, 0), (52, 101, 164), (117, 80, 123), (6, 152, 154), (211, 215, 207),
+                (85, 87, 83), (239, 41, 41), (138, 226, 52), (252, 233, 79), (114, 159, 207), (173, 127, 168), (52, 226, 226), (238, 238, 236),
+            ],
+            foreground: (211, 215, 207),
+            background: (46, 52, 54),
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "xterm" => Some(Self::xterm()),
+            "vga" => Some(Self::vga()),
+            "tango" => Some(Self::tango()),
+            _ => None,
+        }
+    }
+
+    /// rgb value of a color on this terminal, the 256 color cube and rgb colors don't depend on the palette
+    pub fn rgb(&self, color: &Color) -> Rgb {
+        match color {
+            Color::_Ansi(n) if *n < 16 => self.ansi[*n as usize],
+            Color::_Ansi(n) => ansi_256_rgb(*n),
+            Color::_RGB(rgb) => *rgb,
+            basic => self.ansi[basic.index().unwrap() as usize],
+        }
+    }
+
+    pub fn bg_rgb(&self, bg: &Background) -> Rgb {
+        self.rgb(&bg.to_color())
+    }
+
+    /// text and background color of the entry, `terminal` gives the defaults
+    pub fn effective_colors(&self, item: &LsItem, terminal: (Rgb, Rgb)) -> (Rgb, Rgb) {
+        let fg = item.color.as_ref().map_or(terminal.0, |c| self.rgb(c));
+        let bg = item.bg.as_ref().map_or(terminal.1, |b| self.bg_rgb(b));
+        if item.style == Some(Style::Reverse) {
+            (bg, fg)
+        } else {
+            (fg, bg)
+        }
+    }
+
+    /// the text with the look of the item, the colors of this palette as truecolor escapes
+    pub fn paint(&self, item: &LsItem, text: &str) -> String {
+        self.paint_with(item, text, |rgb| rgb)
+    }
+
+    /// like `paint`, every color goes through `transform` first
+    pub fn paint_with(&self, item: &LsItem, text: &str, transform: impl Fn(Rgb) -> Rgb) -> String {
+        let mut codes = vec![];
+        if let Some(style) = &item.style {
+            codes.push(style.to_string());
+        }
+        if let Some(color) = &item.color {
+            codes.push(self.output_code(transform(self.rgb(color)), false));
+        }
+        if let Some(bg) = &item.bg {
+            codes.push(self.output_code(transform(self.bg_rgb(bg)), true));
+        }
+        capability::paint(&codes.join(";"), text)
+    }
+
+    /// SGR code for the rgb color with the depth of the output (truecolor, nearest of 256 or of the 16 colors)
+    fn output_code(&self, rgb: Rgb, background: bool) -> String {
+        let color = match capability::depth() {
+            Some(Depth::Ansi16) => Color::from_index(depth::nearest_16(rgb, self)),
+            Some(Depth::Ansi256) => Color::_Ansi(depth::nearest_256(rgb)),
+            _ => Color::_RGB(rgb),
+        };
+        if background { Background::from_color(&color).to_string() } else { color.to_string() }
+    }
+
+    /// default text and background for a dark or light terminal, the palette's own when it matches
+    pub fn terminal_colors(&self, background: TermBackground) -> (Rgb, Rgb) {
+        let palette_is_dark = luminance(self.background) < 0.5;
+        match background {
+            TermBackground::Light if !palette_is_dark => (self.foreground, self.background),
+            TermBackground::Light => ((0, 0, 0), (255, 255, 255)),
+            _ if palette_is_dark => (self.foreground, self.background),
+            _ => ((229, 229, 229), (0, 0, 0)),
+        }
+    }
+
+    /// sets one of the 16 colors (index or name like 'bright-blue'), 'fg' or 'bg'
+    pub fn set(&mut self, slot: &str, rgb: Rgb) -> Result<(), String> {
+        match slot {
+            "fg" | "foreground" => self.foreground = rgb,
+            "bg" | "background" => self.background = rgb,
+            _ => {
+                let index = slot.parse::<usize>().ok().or_else(|| crate::COLOR_NAMES.iter().position(|n| *n == slot));
+                match index {
+                    Some(i) if i < 16 => self.ansi[i] = rgb,
+                    _ => return Err(format!("unknown palette slot: {} (0-15, color name, fg or bg)", slot)),
+                }
+            },
+        }
+        Ok(())
+    }
+
+    pub fn print(&self) {
+        let swatch = |rgb: Rgb| format!("{} #{:02x}{:02x}{:02x}", capability::paint(&self.output_code(rgb, true), "    "), rgb.0, rgb.1, rgb.2);
+        println!("palette: {}", self.name);
+        println!("  {:<17} {}", "foreground", swatch(self.foreground));
+        println!("  {:<17} {}", "background", swatch(self.background));
+        for (i, rgb) in self.ansi.iter().enumerate() {
+            println!("  {:>2} {:<14} {}", i, crate::COLOR_NAMES[i], swatch(*rgb));
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum TermBackground {
+    Dark,
+    Light,
+    /// check against dark and light
+    #[default]
+    Both,
+}
+
+impl TermBackground {
+    pub fn variants(self) -> Vec<TermBackground> {
+        match self {
+            Self::Both => vec![Self::Dark, Self::Light],
+            single => vec![single],
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+            Self::Both => "both",
+        }
+    }
+}
+
+/// parses '#rrggbb' (the '#' is optional)
+pub fn parse_hex(s: &str) -> Option<Rgb> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// sRGB channel (0 - 255) to linear light (0 - 1)
+pub fn linearize(c: u8) -> f64 {
+    let c = c as f64 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// linear light (0 - 1) back to an sRGB channel
+pub fn delinearize(c: f64) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (c * 255.0).round() as u8
+}
+
+/// relative luminance (WCAG 2)
+pub fn luminance(rgb: Rgb) -> f64 {
+    0.2126 * linearize(rgb.0) + 0.7152 * linearize(rgb.1) + 0.0722 * linearize(rgb.2)
+}
+
+/// OKLab (L, a, b), a perceptual space where distances match how different colors look
+pub fn oklab(rgb: Rgb) -> (f64, f64, f64) {
+    let (r, g, b) = (linearize(rgb.0), linearize(rgb.1), linearize(rgb.2));
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    (
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    )
+}
+
+/// euclidean distance in OKLab, about 0.02 is barely noticeable
+pub fn oklab_distance(a: Rgb, b: Rgb) -> f64 {
+    let (a, b) = (oklab(a), oklab(b));
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
+}
+
+/// WCAG contrast ratio between 1 and 21
+pub fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+    let (la, lb) = (luminance(a), luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// contrast of an entry on a dark or light terminal, None for entries that don't set any color
+pub fn entry_contrast(palette: &Palette, item: &LsItem, background: TermBackground) -> Option<f64> {
+    if item.color.is_none() && item.bg.is_none() {
+        return None;
+    }
+    let (fg, bg) = palette.effective_colors(item, palette.terminal_colors(background));
+    Some(contrast_ratio(fg, bg))
+}
@@ -0,0 +1,226 @@
+use std::{env, fs::{self, File, OpenOptions}, io::{Read, Write}, path::PathBuf, time::{Duration, Instant}};
+
+use crate::palette::Palette;
+use crate::schemes::{self, SchemeFormat, Slots};
+
+/// OSC 4 for the 16 colors, OSC 10/11 for the default text and background, then a device attributes
+/// request (DA1): every terminal answers it, so its reply ends the wait on terminals that ignore the others
+pub fn request() -> String {
+    let mut request: String = (0..16).map(|i| format!("\x1b]4;{};?\x07", i)).collect();
+    request.push_str("\x1b]10;?\x07\x1b]11;?\x07\x1b[c");
+    request
+}
+
+/// true once the reply to the DA1 request ('ESC [ ? .. c') arrived
+pub fn answered(reply: &[u8]) -> bool {
+    let reply = String::from_utf8_lossy(reply);
+    reply.find("\x1b[?").is_some_and(|start| reply[start..].contains('c'))
+}
+
+/// the colors in the OSC replies ('ESC ] 4 ; 1 ; rgb:cdcd/0000/0000 BEL', 'ESC ] 11 ; rgb:.. ESC \')
+pub fn parse_reply(reply: &str) -> Slots {
+    let mut slots = Slots::new();
+    for sequence in reply.split("\x1b]").skip(1) {
+        let body = sequence.split(['\x07', '\x1b']).next().unwrap_or_default();
+        let parts: Vec<&str> = body.split(';').collect();
+        let (slot, value) = match parts[..] {
+            ["4", n, value] => (n.to_string(), value),
+            ["10", value] => ("fg".to_string(), value),
+            ["11", value] => ("bg".to_string(), value),
+            _ => continue,
+        };
+        if let Some(rgb) = schemes::parse_color(value) {
+            slots.insert(slot, rgb);
+        }
+    }
+    slots
+}
+
+/// sends the queries to the terminal behind `tty` (in raw mode while waiting) and reads the replies
+#[cfg(unix)]
+pub fn query(tty: &mut File, timeout: Duration) -> Result<Palette, Box<dyn std::error::Error>> {
+    use nix::sys::termios::{self, SetArg};
+
+    let saved = termios::tcgetattr(&*tty)?;
+    let mut raw = saved.clone();
+    termios::cfmakeraw(&mut raw);
+    termios::tcsetattr(&*tty, SetArg::TCSANOW, &raw)?;
+    let reply = exchange(tty, timeout);
+    termios::tcsetattr(&*tty, SetArg::TCSAFLUSH, &saved)?;
+
+    let slots = parse_reply(&reply?);
+    if slots.is_empty() {
+        return Err("the terminal doesn't answer color queries".into());
+    }
+    schemes::into_palette(&terminal_id(), slots)
+}
+
+#[cfg(not(unix))]
+pub fn query(_tty: &mut File, _timeout: Duration) -> Result<Palette, Box<dyn std::error::Error>> {
+    Err("querying the terminal is not supported on this platform".into())
+}
+
+#[cfg(unix)]
+fn exchange(tty: &mut File, timeout: Duration) -> Result<String, Box<dyn std::error::Error>> {
+    use std::os::fd::AsFd;
+    use nix::poll::{poll, PollFd, PollFlags};
+
+    tty.write_all(request().as_bytes())?;
+    tty.flush()?;
+    let deadline = Instant::now() + timeout;
+    let mut reply = vec![];
+    let mut buffer = [0u8; 1024];
+    while !answered(&reply) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let mut fds = [PollFd::new(tty.as_fd(), PollFlags::POLLIN)];
+        if left.is_zero() || poll(&mut fds, left.as_millis().min(u16::MAX as u128) as u16)? == 0 {
+            break;
+        }
+        let n = tty.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        reply.extend_from_slice(&buffer[..n]);
+    }
+    Ok(String::from_utf8_lossy(&reply).to_string())
+}
+
+/// the terminal program the cache is kept for
+fn terminal_id() -> String {
+    let id = env::var("TERM_PROGRAM").or_else(|_| env::var("TERM")).unwrap_or_else(|_| "unknown".to_string());
+    id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' }).collect()
+}
+
+/// $XDG_CACHE_HOME/bash-colors/palette-TERMINAL.conf (kitty.conf syntax)
+pub fn cache_path() -> Option<PathBuf> {
+    let cache = env::var_os("XDG_CACHE_HOME").map(PathBuf::from).or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache.join("bash-colors").join(format!("palette-{}.conf", terminal_id())))
+}
+
+/// the palette of the running terminal, from the cache unless `refresh` is set; the bool tells if it was cached
+pub fn live_palette(timeout: Duration, refresh: bool) -> Result<(Palette, bool), Box<dyn std::error::Error>> {
+    cached_palette(cache_path(), refresh, || {
+        let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty").map_err(|e| format!("no terminal: {}", e))?;
+        query(&mut tty, timeout)
+    })
+}
+
+/// the palette of the cache file, or the one `ask` gets from the terminal, which is then written to the cache
+fn cached_palette<F>(cache: Option<PathBuf>, refresh: bool, ask: F) -> Result<(Palette, bool), Box<dyn std::error::Error>>
+where F: FnOnce() -> Result<Palette, Box<dyn std::error::Error>> {
+    if let Some(path) = cache.as_ref().filter(|p| !refresh && p.exists()) {
+        let mut palette = schemes::import_scheme(path, Some(SchemeFormat::Kitty))?;
+        palette.name = terminal_id();
+        return Ok((palette, true));
+    }
+    let palette = ask()?;
+    if let Some(path) = cache {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, schemes::kitty_conf(&palette))?;
+    }
+    Ok((palette, false))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{env, fs::File, io::{Read, Write}, thread::{self, JoinHandle}, time::{Duration, Instant}};
+
+    use nix::pty::openpty;
+
+    use super::*;
+
+    const DA1: &str = "\x1b[?62;22c";
+
+    /// the slave side of a pty and a thread playing the terminal on the master side: it waits for the
+    /// DA1 request, answers with `reply` (unless None) and keeps the pty open until the slave is closed
+    fn fake_terminal(reply: Option<String>) -> (File, JoinHandle<String>) {
+        let pty = openpty(None, None).expect("openpty");
+        let mut master = File::from(pty.master);
+        let terminal = thread::spawn(move || {
+            let mut request = vec![];
+            let mut buffer = [0u8; 1024];
+            let mut answered = false;
+            while let Ok(n) = master.read(&mut buffer) {
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..n]);
+                if !answered && String::from_utf8_lossy(&request).contains("\x1b[c") {
+                    if let Some(reply) = &reply {
+                        master.write_all(reply.as_bytes()).expect("reply");
+                    }
+                    answered = true;
+                }
+            }
+            String::from_utf8_lossy(&request).to_string()
+        });
+        (File::from(pty.slave), terminal)
+    }
+
+    /// color i is rgb(i * 16, 0x80, 255 - i * 16), text and background end with ST instead of BEL
+    fn color_reply() -> String {
+        let mut reply: String = (0..16u32).map(|i| format!("\x1b]4;{};rgb:{:02x}/80/{:02x}\x07", i, i * 16, 255 - i * 16)).collect();
+        reply.push_str("\x1b]10;rgb:dddd/eeee/ffff\x1b\\\x1b]11;rgb:1111/2222/3333\x1b\\");
+        reply.push_str(DA1);
+        reply
+    }
+
+    fn ask(reply: Option<String>, timeout: Duration) -> (Result<Palette, Box<dyn std::error::Error>>, String) {
+        let (mut tty, terminal) = fake_terminal(reply);
+        let palette = query(&mut tty, timeout);
+        drop(tty);
+        (palette, terminal.join().expect("terminal thread"))
+    }
+
+    #[test]
+    fn parses_the_osc_replies() {
+        let (palette, sent) = ask(Some(color_reply()), Duration::from_secs(5));
+        let palette = palette.expect("palette");
+        assert_eq!(sent, request());
+        for (i, rgb) in palette.ansi.iter().enumerate() {
+            assert_eq!(*rgb, ((i * 16) as u8, 0x80, (255 - i * 16) as u8));
+        }
+        assert_eq!(palette.foreground, (0xdd, 0xee, 0xff));
+        assert_eq!(palette.background, (0x11, 0x22, 0x33));
+    }
+
+    #[test]
+    fn da1_only_means_unsupported_without_waiting() {
+        let start = Instant::now();
+        let (palette, _) = ask(Some(DA1.to_string()), Duration::from_secs(5));
+        assert!(palette.is_err());
+        assert!(start.elapsed() < Duration::from_secs(2), "the DA1 reply ends the wait");
+    }
+
+    #[test]
+    fn no_reply_times_out() {
+        let start = Instant::now();
+        let (palette, _) = ask(None, Duration::from_millis(200));
+        assert!(palette.is_err());
+        // poll waits in whole milliseconds
+        assert!(start.elapsed() >= Duration::from_millis(190), "waits for the timeout");
+    }
+
+    #[test]
+    fn caches_under_xdg_cache_home() {
+        let cache_home = env::temp_dir().join(format!("bash-colors-query-{}", std::process::id()));
+        env::set_var("XDG_CACHE_HOME", &cache_home);
+        let path = cache_path().expect("cache path");
+        assert!(path.starts_with(&cache_home));
+
+        let (queried, cached) = cached_palette(cache_path(), false, || ask(Some(color_reply()), Duration::from_secs(5)).0).expect("query");
+        assert!(!cached);
+        assert!(path.exists());
+
+        let (read, cached) = cached_palette(cache_path(), false, || Err("the terminal is asked again".into())).expect("cache");
+        assert!(cached);
+        assert_eq!(read.ansi, queried.ansi);
+        assert_eq!((read.foreground, read.background), (queried.foreground, queried.background));
+
+        let refreshed = cached_palette(cache_path(), true, || Err("refresh asks the terminal".into()));
+        assert!(refreshed.is_err());
+        fs::remove_dir_all(&cache_home).expect("cleanup");
+    }
+}
@@ -0,0 +1,197 @@
+/// the keys a color variable understands, a ColorMap is built from one
+#[derive(Debug, PartialEq)]
+pub struct Schema {
+    /// name of the environment variable
+    pub variable: &'static str,
+    /// the program reading it
+    pub program: &'static str,
+    /// the keys with their descriptions, in the order they are listed
+    pub keys: &'static [(&'static str, &'static str)],
+    /// keys without a value, turned on by their name alone (GREP_COLORS rv, ne)
+    pub flags: &'static [&'static str],
+    /// keys setting others at once, with their members (GREP_COLORS mt sets ms and mc)
+    pub shorthands: &'static [(&'static str, &'static [&'static str])],
+    /// file name patterns (*.ext) are keys too
+    pub patterns: bool,
+    /// paths and directory names (~/Documents, .git/) are keys too, see lf.rs
+    pub paths: bool,
+    /// what the program uses for the keys the variable doesn't set
+    pub builtin: &'static str,
+}
+
+pub const LS_COLORS: Schema = Schema {
+    variable: "LS_COLORS",
+    program: "ls",
+    keys: &[
+        ("rs", "reset to no color"),
+        ("ca", "file with capability"),
+        ("cd", "character device"),
+        ("di", "directory"),
+        ("do", "door"),
+        ("ex", "executable file"),
+        ("fi", "regular file"),
+        ("ln", "symbolic link"),
+        ("mh", "multi-hardlink"),
+        ("mi", "missing file"),
+        ("no", "normal non-filename text"),
+        ("or", "orphan symlink"),
+        ("ow", "other-writable directory"),
+        ("pi", "named pipe, AKA FIFO"),
+        ("bd", "block device"),
+        ("sg", "set-group-ID"),
+        ("so", "socket"),
+        ("st", "sticky directory"),
+        ("su", "set-user-ID"),
+        ("tw", "sticky and other-writable directory"),
+    ],
+    flags: &[],
+    shorthands: &[],
+    patterns: true,
+    paths: false,
+    // coreutils ls.c
+    builtin: "rs=0:di=01;34:ln=01;36:pi=33:so=01;35:bd=01;33:cd=01;33:ex=01;32:do=01;35:su=37;41:sg=30;43:st=37;44:ow=34;42:tw=30;42",
+};
+
+pub const GREP_COLORS: Schema = Schema {
+    variable: "GREP_COLORS",
+    program: "grep",
+    keys: &[
+        ("sl", "whole selected line"),
+        ("cx", "whole context line"),
+        ("rv", "swap sl and cx with -v (flag)"),
+        ("mt", "matching text in any line"),
+        ("ms", "matching text in a selected line"),
+        ("mc", "matching text in a context line"),
+        ("fn", "file name"),
+        ("ln", "line number"),
+        ("bn", "byte offset"),
+        ("se", "separator (:, -, --)"),
+        ("ne", "no erase to the end of the line (flag)"),
+    ],
+    flags: &["rv", "ne"],
+    shorthands: &[("mt", &["ms", "mc"])],
+    patterns: false,
+    paths: false,
+    builtin: "ms=01;31:mc=01;31:sl=:cx=:fn=35:ln=32:bn=32:se=36",
+};
+
+pub const GCC_COLORS: Schema = Schema {
+    variable: "GCC_COLORS",
+    program: "gcc",
+    keys: &[
+        ("error", "error:"),
+        ("warning", "warning:"),
+        ("note", "note:"),
+        ("range1", "first underlined source range"),
+        ("range2", "second underlined source range"),
+        ("locus", "file:line:column"),
+        ("quote", "quoted code"),
+        ("path", "diagnostic paths"),
+        ("fixit-insert", "fix-it hint: inserted text"),
+        ("fixit-delete", "fix-it hint: deleted text"),
+        ("diff-filename", "diff: file name"),
+        ("diff-hunk", "diff: hunk header"),
+        ("diff-delete", "diff: deleted line"),
+        ("diff-insert", "diff: inserted line"),
+        ("type-diff", "differences in template types"),
+        ("fnname", "function name"),
+        ("targs", "template arguments"),
+        ("valid", "valid part of a format string"),
+        ("invalid", "invalid part of a format string"),
+        ("highlight-a", "first highlighted part"),
+        ("highlight-b", "second highlighted part"),
+    ],
+    flags: &[],
+    shorthands: &[],
+    patterns: false,
+    paths: false,
+    builtin: "error=01;31:warning=01;35:note=01;36:range1=32:range2=34:locus=01:quote=01:path=01;36:fixit-insert=32:fixit-delete=31:\
+              diff-filename=01:diff-hunk=32:diff-delete=31:diff-insert=32:type-diff=01;32:fnname=01;32:targs=35:valid=01;32:invalid=01;31:\
+              highlight-a=01;32:highlight-b=01;34",
+};
+
+pub const TREE_COLORS: Schema = Schema {
+    variable: "TREE_COLORS",
+    program: "tree",
+    keys: &[
+        ("no", "normal text"),
+        ("fi", "regular file"),
+        ("di", "directory"),
+        ("ln", "symbolic link"),
+        ("pi", "named pipe, AKA FIFO"),
+        ("so", "socket"),
+        ("do", "door"),
+        ("bd", "block device"),
+        ("cd", "character device"),
+        ("or", "orphan symlink"),
+        ("mi", "missing file"),
+        ("ex", "executable file"),
+        ("lc", "left code of the escape sequence"),
+        ("rc", "right code of the escape sequence"),
+        ("ec", "end code of the escape sequence"),
+    ],
+    flags: &[],
+    shorthands: &[],
+    patterns: true,
+    paths: false,
+    // tree.c, used when neither TREE_COLORS nor LS_COLORS is set
+    builtin: "no=00:fi=00:di=01;34:ln=01;36:pi=40;33:so=01;35:do=01;35:bd=40;33;01:cd=40;33;01:or=40;31;01:ex=01;32",
+};
+
+pub const LF_COLORS: Schema = Schema {
+    variable: "LF_COLORS",
+    program: "lf",
+    keys: &[
+        ("ln", "symbolic link"),
+        ("or", "orphan symlink"),
+        ("tw", "sticky and other-writable directory"),
+        ("ow", "other-writable directory"),
+        ("st", "sticky directory"),
+        ("di", "directory"),
+        ("pi", "named pipe, AKA FIFO"),
+        ("so", "socket"),
+        ("bd", "block device"),
+        ("cd", "character device"),
+        ("su", "set-user-ID"),
+        ("sg", "set-group-ID"),
+        ("ex", "executable file"),
+        ("fi", "regular file"),
+    ],
+    flags: &[],
+    shorthands: &[],
+    patterns: true,
+    paths: true,
+    // lf falls back to the colors of ls
+    builtin: "ln=01;36:pi=33:so=01;35:bd=01;33:cd=01;33:ex=01;32:su=37;41:sg=30;43:st=37;44:ow=34;42:tw=30;42:di=01;34",
+};
+
+pub const ALL: [&Schema; 5] = [&LS_COLORS, &GREP_COLORS, &GCC_COLORS, &TREE_COLORS, &LF_COLORS];
+
+/// the schema of a variable, the name is matched without case ('grep_colors')
+pub fn find(variable: &str) -> Option<&'static Schema> {
+    ALL.into_iter().find(|s| s.variable.eq_ignore_ascii_case(variable))
+}
+
+impl Schema {
+    pub fn description(&self, key: &str) -> Option<&'static str> {
+        self.keys.iter().find(|(k, _)| *k == key).map(|(_, description)| *description)
+    }
+
+    pub fn has_key(&self, key: &str) -> bool {
+        self.description(key).is_some()
+    }
+
+    pub fn is_flag(&self, key: &str) -> bool {
+        self.flags.contains(&key)
+    }
+
+    /// the keys a shorthand sets, none for the other keys
+    pub fn members(&self, key: &str) -> &'static [&'static str] {
+        self.shorthands.iter().find(|(k, _)| *k == key).map_or(&[], |(_, members)| members)
+    }
+
+    /// LS_COLORS itself, the other formats (dircolors, eza, lsd ..) only translate it
+    pub fn is_ls(&self) -> bool {
+        self.variable == LS_COLORS.variable
+    }
+}
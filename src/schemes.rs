@@ -0,0 +1,312 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use clap::ValueEnum;
+
+use crate::palette::{parse_hex, Palette, Rgb};
+
+/// color scheme formats of terminal emulators
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SchemeFormat {
+    /// alacritty.toml ([colors.primary], [colors.normal], [colors.bright])
+    Alacritty,
+    /// kitty.conf (foreground, background, color0 - color15)
+    Kitty,
+    /// ~/.Xresources (*.foreground, *.color0 ..)
+    Xresources,
+    /// foot.ini ([colors] regular0 - regular7, bright0 - bright7)
+    Foot,
+    /// Windows Terminal settings.json or a single scheme
+    WindowsTerminal,
+    /// iTerm2 .itermcolors plist
+    Iterm,
+}
+
+const NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// '#rrggbb', '0xrrggbb', 'rrggbb' or the X11 form 'rgb:r/g/b' with 1 to 4 hex digits per channel
+pub fn parse_color(value: &str) -> Option<Rgb> {
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+    if let Some(rgb) = value.strip_prefix("rgb:") {
+        let channel = |c: &str| {
+            if !(1..=4).contains(&c.len()) {
+                return None;
+            }
+            let max = 16u32.pow(c.len() as u32) - 1;
+            u32::from_str_radix(c, 16).ok().map(|v| ((v * 255 + max / 2) / max) as u8)
+        };
+        let channels: Vec<u8> = rgb.split('/').map(channel).collect::<Option<_>>()?;
+        return match channels[..] {
+            [r, g, b] => Some((r, g, b)),
+            _ => None,
+        };
+    }
+    parse_hex(value.strip_prefix("0x").unwrap_or(value))
+}
+
+/// slots found in a scheme (fg, bg or 0 - 15) with their colors
+pub type Slots = HashMap<String, Rgb>;
+
+pub fn into_palette(name: &str, slots: Slots) -> Result<Palette, Box<dyn std::error::Error>> {
+    if slots.is_empty() {
+        return Err("no colors found".into());
+    }
+    // colors missing in the scheme keep the xterm values
+    let mut palette = Palette { name: name.to_string(), ..Palette::xterm() };
+    for (slot, rgb) in slots {
+        palette.set(&slot, rgb)?;
+    }
+    Ok(palette)
+}
+
+/// guesses the format from the file name, then from the content
+pub fn detect(path: &Path, content: &str) -> Option<SchemeFormat> {
+    let file_name = path.file_name()?.to_string_lossy().to_lowercase();
+    match path.extension().map(|e| e.to_string_lossy().to_lowercase()).as_deref() {
+        Some("itermcolors") => return Some(SchemeFormat::Iterm),
+        Some("json") => return Some(SchemeFormat::WindowsTerminal),
+        Some("toml") => return Some(SchemeFormat::Alacritty),
+        Some("ini") => return Some(SchemeFormat::Foot),
+        _ => (),
+    }
+    if file_name.contains("kitty") {
+        return Some(SchemeFormat::Kitty);
+    }
+    if file_name.contains("xresources") || file_name.contains("xdefaults") {
+        return Some(SchemeFormat::Xresources);
+    }
+    if content.contains("<plist") {
+        Some(SchemeFormat::Iterm)
+    } else if content.contains("regular0") {
+        Some(SchemeFormat::Foot)
+    } else if content.contains("[colors") {
+        Some(SchemeFormat::Alacritty)
+    } else if content.lines().any(|l| l.trim_start().starts_with("color0 ") || l.trim_start().starts_with("color0\t")) {
+        Some(SchemeFormat::Kitty)
+    } else if content.contains("color0:") {
+        Some(SchemeFormat::Xresources)
+    } else {
+        None
+    }
+}
+
+pub fn import_scheme(path: &Path, format: Option<SchemeFormat>) -> Result<Palette, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let format = format.or_else(|| detect(path, &content)).ok_or("unknown scheme format, use --format")?;
+    let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    match format {
+        SchemeFormat::Alacritty => parse_alacritty(&name, &content),
+        SchemeFormat::Kitty => into_palette(&name, parse_kitty(&content)),
+        SchemeFormat::Xresources => into_palette(&name, parse_xresources(&content)),
+        SchemeFormat::Foot => into_palette(&name, parse_foot(&content)),
+        SchemeFormat::WindowsTerminal => parse_windows_terminal(&name, &content),
+        SchemeFormat::Iterm => into_palette(&name, parse_iterm(&content)?),
+    }
+}
+
+fn parse_alacritty(name: &str, content: &str) -> Result<Palette, Box<dyn std::error::Error>> {
+    let table: toml::Table = toml::from_str(content)?;
+    let colors = table.get("colors").and_then(|c| c.as_table()).ok_or("no [colors] table")?;
+    let color = |section: &str, key: &str| colors.get(section)?.get(key)?.as_str().and_then(parse_color);
+    let mut slots = Slots::new();
+    for (slot, key) in [("fg", "foreground"), ("bg", "background")] {
+        if let Some(rgb) = color("primary", key) {
+            slots.insert(slot.to_string(), rgb);
+        }
+    }
+    for (i, key) in NAMES.iter().enumerate() {
+        for (section, offset) in [("normal", 0), ("bright", 8)] {
+            if let Some(rgb) = color(section, key) {
+                slots.insert((i + offset).to_string(), rgb);
+            }
+        }
+    }
+    into_palette(name, slots)
+}
+
+/// the palette in kitty.conf syntax
+pub fn kitty_conf(palette: &Palette) -> String {
+    let hex = |(r, g, b): Rgb| format!("#{:02x}{:02x}{:02x}", r, g, b);
+    let mut lines = vec![format!("foreground {}", hex(palette.foreground)), format!("background {}", hex(palette.background))];
+    lines.extend(palette.ansi.iter().enumerate().map(|(i, rgb)| format!("color{} {}", i, hex(*rgb))));
+    lines.join("\n") + "\n"
+}
+
+/// 'key value' lines, '#' starts a comment
+fn parse_kitty(content: &str) -> Slots {
+    let mut slots = Slots::new();
+    for line in content.lines().map(str::trim).filter(|l| !l.starts_with('#')) {
+        let Some((key, value)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let slot = match key {
+            "foreground" => "fg".to_string(),
+            "background" => "bg".to_string(),
+            _ => match key.strip_prefix("color").and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if n < 16 => n.to_string(),
+                _ => continue,
+            },
+        };
+        if let Some(rgb) = parse_color(value) {
+            slots.insert(slot, rgb);
+        }
+    }
+    slots
+}
+
+/// 'name.resource: value' lines, '!' starts a comment, '#define NAME value' is substituted
+fn parse_xresources(content: &str) -> Slots {
+    let mut defines: HashMap<&str, &str> = HashMap::new();
+    let mut slots = Slots::new();
+    for line in content.lines().map(str::trim).filter(|l| !l.starts_with('!')) {
+        if let Some(define) = line.strip_prefix("#define") {
+            let mut parts = define.split_whitespace();
+            if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                defines.insert(name, value);
+            }
+            continue;
+        }
+        let Some((resource, value)) = line.split_once(':') else {
+            continue;
+        };
+        // '*color4', 'URxvt.color4', '*.foreground'
+        let key = resource.rsplit(['*', '.']).next().unwrap_or_default().trim();
+        let slot = match key {
+            "foreground" => "fg".to_string(),
+            "background" => "bg".to_string(),
+            _ => match key.strip_prefix("color").and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if n < 16 => n.to_string(),
+                _ => continue,
+            },
+        };
+        let value = value.trim();
+        if let Some(rgb) = parse_color(defines.get(value).unwrap_or(&value)) {
+            slots.insert(slot, rgb);
+        }
+    }
+    slots
+}
+
+/// the [colors] (or [colors-dark]) section of foot.ini
+fn parse_foot(content: &str) -> Slots {
+    let mut slots = Slots::new();
+    let mut in_colors = false;
+    for line in content.lines().map(str::trim).filter(|l| !l.starts_with('#')) {
+        if line.starts_with('[') {
+            in_colors = line == "[colors]" || line == "[colors-dark]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_colors) else {
+            continue;
+        };
+        let key = key.trim();
+        let slot = match key {
+            "foreground" => "fg".to_string(),
+            "background" => "bg".to_string(),
+            _ => {
+                let index = key.strip_prefix("regular").map(|n| (n, 0)).or_else(|| key.strip_prefix("bright").map(|n| (n, 8)));
+                match index.and_then(|(n, offset)| n.parse::<usize>().ok().map(|n| n + offset)) {
+                    Some(n) if n < 16 => n.to_string(),
+                    _ => continue,
+                }
+            },
+        };
+        if let Some(rgb) = parse_color(value) {
+            slots.insert(slot, rgb);
+        }
+    }
+    slots
+}
+
+/// a scheme object or a settings.json with a "schemes" list (the first scheme is used)
+fn parse_windows_terminal(name: &str, content: &str) -> Result<Palette, Box<dyn std::error::Error>> {
+    let json: serde_json::Value = serde_json::from_str(content)?;
+    let scheme = match json.get("schemes") {
+        Some(schemes) => schemes.get(0).ok_or("the schemes list is empty")?,
+        None => &json,
+    };
+    let name = scheme.get("name").and_then(|n| n.as_str()).unwrap_or(name);
+    let color = |key: &str| scheme.get(key)?.as_str().and_then(parse_color);
+    let mut slots = Slots::new();
+    for (slot, key) in [("fg", "foreground"), ("bg", "background")] {
+        if let Some(rgb) = color(key) {
+            slots.insert(slot.to_string(), rgb);
+        }
+    }
+    // Windows Terminal calls magenta 'purple'
+    let keys = ["black", "red", "green", "yellow", "blue", "purple", "cyan", "white"];
+    for (i, key) in keys.iter().enumerate() {
+        if let Some(rgb) = color(key) {
+            slots.insert(i.to_string(), rgb);
+        }
+        let bright = format!("bright{}{}", key[..1].to_uppercase(), &key[1..]);
+        if let Some(rgb) = color(&bright) {
+            slots.insert((i + 8).to_string(), rgb);
+        }
+    }
+    into_palette(name, slots)
+}
+
+/// the text of the xml tags in order: ("key", "Ansi 0 Color"), ("dict", ""), ("real", "0.5"), ("/dict", "") ..
+fn xml_tokens(content: &str) -> Vec<(String, String)> {
+    let mut tokens = vec![];
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        let name = tag.split_whitespace().next().unwrap_or_default().to_string();
+        if tag.ends_with('/') || name.starts_with('/') {
+            tokens.push((name.trim_end_matches('/').to_string(), String::new()));
+            continue;
+        }
+        let text = rest.find('<').map(|i| rest[..i].trim().to_string()).unwrap_or_default();
+        tokens.push((name, text));
+    }
+    tokens
+}
+
+/// the color dicts of an .itermcolors plist ('Ansi 0 Color' .. 'Ansi 15 Color', 'Foreground Color', 'Background Color')
+fn parse_iterm(content: &str) -> Result<Slots, Box<dyn std::error::Error>> {
+    let mut slots = Slots::new();
+    let mut color_key: Option<String> = None;
+    let mut component: Option<String> = None;
+    let mut channels: HashMap<String, f64> = HashMap::new();
+    let mut depth = 0;
+    for (tag, text) in xml_tokens(content) {
+        match tag.as_str() {
+            "dict" => depth += 1,
+            "/dict" => {
+                depth -= 1;
+                if depth == 1 {
+                    if let Some(key) = color_key.take() {
+                        let channel = |name: &str| (channels.get(name).copied().unwrap_or(0.0).clamp(0.0, 1.0) * 255.0).round() as u8;
+                        let rgb = (channel("Red Component"), channel("Green Component"), channel("Blue Component"));
+                        let slot = match key.as_str() {
+                            "Foreground Color" => Some("fg".to_string()),
+                            "Background Color" => Some("bg".to_string()),
+                            _ => key.strip_prefix("Ansi ").and_then(|k| k.strip_suffix(" Color")).and_then(|n| n.parse::<u8>().ok()).filter(|n| *n < 16).map(|n| n.to_string()),
+                        };
+                        if let Some(slot) = slot {
+                            slots.insert(slot, rgb);
+                        }
+                    }
+                    channels.clear();
+                }
+            },
+            "key" if depth == 1 => color_key = Some(text),
+            "key" => component = Some(text),
+            "real" | "integer" => {
+                if let Some(component) = component.take() {
+                    channels.insert(component, text.parse()?);
+                }
+            },
+            _ => (),
+        }
+    }
+    Ok(slots)
+}
@@ -0,0 +1,220 @@
+use std::{collections::{BTreeMap, HashMap}, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{self, Schema, LS_COLORS};
+use crate::{is_variable, Background, Color, ColorMap, LsItem, Style, Values, COLOR_NAMES};
+
+/// theme metadata that is not part of LS_COLORS, kept next to the ColorMap
+#[derive(Debug, Default, Clone)]
+pub struct ThemeInfo {
+    pub name: String,
+    pub author: String,
+    pub description: String,
+    /// free text notes per entry key
+    pub notes: HashMap<String, String>,
+    /// named groups of extensions (ex.: 'archive' -> ['*.tar', '*.zip'])
+    pub categories: BTreeMap<String, Vec<String>>,
+}
+
+/// layout of a toml theme file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ThemeFile {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    author: String,
+    #[serde(default)]
+    description: String,
+    /// the variable the colors are for
+    #[serde(default = "default_variable")]
+    variable: String,
+    /// entry key -> color spec or style variable ($name), in declared order
+    #[serde(default)]
+    colors: toml::Table,
+    #[serde(default)]
+    notes: BTreeMap<String, String>,
+    #[serde(default)]
+    categories: BTreeMap<String, Vec<String>>,
+}
+
+fn default_variable() -> String {
+    LS_COLORS.variable.to_string()
+}
+
+fn style_name(style: &Style) -> &'static str {
+    match style {
+        Style::None => "none",
+        Style::Bold => "bold",
+        Style::Underscore => "underscore",
+        Style::Blink => "blink",
+        Style::Reverse => "reverse",
+        Style::Concealed => "concealed",
+    }
+}
+
+fn style_from_name(name: &str) -> Option<Style> {
+    match name {
+        "none" => Some(Style::None),
+        "bold" => Some(Style::Bold),
+        "underscore" | "underline" => Some(Style::Underscore),
+        "blink" => Some(Style::Blink),
+        "reverse" => Some(Style::Reverse),
+        "concealed" => Some(Style::Concealed),
+        _ => None,
+    }
+}
+
+/// color name as used in specs: basic color names, 'ansi:208' or '#rrggbb'
+pub fn color_name(color: &Color) -> String {
+    match color {
+        Color::_Ansi(n) => format!("ansi:{}", n),
+        Color::_RGB((r, g, b)) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        basic => COLOR_NAMES[basic.index().unwrap() as usize].to_string(),
+    }
+}
+
+pub fn color_from_name(name: &str) -> Result<Color, String> {
+    if let Some(i) = COLOR_NAMES.iter().position(|n| *n == name) {
+        return Ok(Color::from_index(i as u8));
+    }
+    if let Some(n) = name.strip_prefix("ansi:") {
+        return n.parse::<u8>().map(Color::_Ansi).map_err(|_| format!("invalid ansi color: {}", name));
+    }
+    if let Some(hex) = name.strip_prefix('#') {
+        let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+        if hex.len() == 6 {
+            if let (Some(r), Some(g), Some(b)) = (channel(0), channel(2), channel(4)) {
+                return Ok(Color::_RGB((r, g, b)));
+            }
+        }
+        return Err(format!("invalid rgb color: {}", name));
+    }
+    Err(format!("unknown color: {}", name))
+}
+
+/// human readable form of an entry, ex.: 'bold bright-blue on black'
+pub fn to_spec(item: &LsItem) -> String {
+    let mut words = vec![];
+    if let Some(style) = &item.style {
+        words.push(style_name(style).to_string());
+    }
+    if let Some(color) = &item.color {
+        words.push(color_name(color));
+    }
+    if let Some(bg) = &item.bg {
+        words.push("on".to_string());
+        words.push(color_name(&bg.to_color()));
+    }
+    words.join(" ")
+}
+
+/// parses a spec as written by `to_spec`
+pub fn from_spec(spec: &str) -> Result<Values, String> {
+    let (mut style, mut color, mut bg) = (None, None, None);
+    let mut words = spec.split_whitespace();
+    while let Some(word) = words.next() {
+        if word == "on" {
+            let name = words.next().ok_or(format!("missing background color in: {}", spec))?;
+            bg = Some(Background::from_color(&color_from_name(name)?));
+        } else if let Some(s) = style_from_name(word) {
+            style = Some(s);
+        } else {
+            color = Some(color_from_name(word)?);
+        }
+    }
+    Ok((style, color, bg))
+}
+
+/// the line of an entry in the [colors] table
+pub fn colors_line(key: &str, item: &LsItem) -> Result<String, toml::ser::Error> {
+    let mut table = toml::Table::new();
+    table.insert(key.to_string(), toml::Value::String(item.reference.clone().unwrap_or_else(|| to_spec(item))));
+    Ok(toml::to_string(&table)?.trim_end().to_string())
+}
+
+pub fn save_theme(path: &Path, map: &ColorMap, info: &ThemeInfo) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(path, theme_to_string(map, info)?)?;
+    Ok(())
+}
+
+pub fn theme_to_string(map: &ColorMap, info: &ThemeInfo) -> Result<String, toml::ser::Error> {
+    let mut entries: Vec<(&String, &LsItem)> = map.iter().collect();
+    entries.sort_by_key(|(_, item)| item.order);
+
+    let mut colors = toml::Table::new();
+    for (key, item) in entries {
+        let spec = item.reference.clone().unwrap_or_else(|| to_spec(item));
+        colors.insert(key.clone(), toml::Value::String(spec));
+    }
+    let file = ThemeFile {
+        name: info.name.clone(),
+        author: info.author.clone(),
+        description: info.description.clone(),
+        variable: map.schema.variable.to_string(),
+        colors,
+        notes: info.notes.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        categories: info.categories.clone(),
+    };
+    toml::to_string_pretty(&file)
+}
+
+pub fn load_theme(path: &Path) -> Result<(ColorMap, ThemeInfo), Box<dyn std::error::Error>> {
+    load_theme_with(path, ColorMap::new)
+}
+
+/// `start` is ColorMap::new for a full map or ColorMap::empty_for for only the entries of the theme
+pub fn load_theme_with(path: &Path, start: fn(&'static Schema) -> ColorMap) -> Result<(ColorMap, ThemeInfo), Box<dyn std::error::Error>> {
+    let file: ThemeFile = toml::from_str(&fs::read_to_string(path)?)?;
+    let schema = schema::find(&file.variable).ok_or(format!("unknown variable: {}", file.variable))?;
+    let mut map = start(schema);
+    let info = ThemeInfo {
+        name: file.name,
+        author: file.author,
+        description: file.description,
+        notes: file.notes.into_iter().collect(),
+        categories: file.categories,
+    };
+    let mut references = vec![];
+    for (key, value) in file.colors {
+        let spec = value.as_str().ok_or(format!("{}: color spec must be a string", key))?;
+        if is_variable(spec) {
+            map.set_values(&key, None, None, None);
+            references.push((key, spec.to_string()));
+            continue;
+        }
+        let (style, color, bg) = from_spec(spec).map_err(|e| format!("{}: {}", key, e))?;
+        map.set_values(&key, style, color, bg);
+    }
+    for (key, variable) in references {
+        map.set_reference(&key, &variable).map_err(|e| format!("{}: {}", key, e))?;
+    }
+    Ok((map, info))
+}
+
+impl ThemeInfo {
+    /// the metadata the other theme sets replaces ours, its notes and categories are added
+    pub fn merge(&mut self, other: ThemeInfo) {
+        for (field, value) in [(&mut self.name, other.name), (&mut self.author, other.author), (&mut self.description, other.description)] {
+            if !value.is_empty() {
+                *field = value;
+            }
+        }
+        self.notes.extend(other.notes);
+        self.categories.extend(other.categories);
+    }
+
+    pub fn print(&self) {
+        println!("name       : {}", self.name);
+        println!("author     : {}", self.author);
+        println!("description: {}", self.description);
+        let mut notes: Vec<_> = self.notes.iter().collect();
+        notes.sort();
+        for (key, note) in notes {
+            println!("  [{}] {}", key, note);
+        }
+        for (name, members) in &self.categories {
+            println!("  {}: {}", name, members.join(" "));
+        }
+    }
+}
@@ -1,104 +1,233 @@
-use std::{collections::HashMap, ops::{Deref, DerefMut}, str::FromStr};
+use std::{collections::HashMap, fmt, ops::{Deref, DerefMut}, str::FromStr};
 
 use clap::Subcommand;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
-pub struct ColorMap(HashMap<String, LsItem>);
+use crate::schema::{self, Schema};
+
+#[derive(Debug, Clone)]
+pub struct ColorMap {
+    entries: HashMap<String, LsItem>,
+    /// the variable the map is for
+    pub schema: &'static Schema,
+}
 
 impl Default for ColorMap {
     fn default() -> Self {
-        let mut color_decs = HashMap::new();
-        color_decs.insert("bd".to_string(),LsItem { style: None, color: None, bg: None, description: "block device".to_string(), order: 15 });
-        color_decs.insert("ca".to_string(),LsItem { style: None, color: None, bg: None, description: "file with capability".to_string(), order: 2 });
-        color_decs.insert("cd".to_string(),LsItem { style: None, color: None, bg: None, description: "character device".to_string(), order: 3 });
-        color_decs.insert("di".to_string(),LsItem { style: None, color: None, bg: None, description: "directory".to_string(), order: 4 });
-        color_decs.insert("do".to_string(),LsItem { style: None, color: None, bg: None, description: "door".to_string(), order: 5 });
-        color_decs.insert("ex".to_string(),LsItem { style: None, color: None, bg: None, description: "executable file".to_string(), order: 6 });
-        color_decs.insert("fi".to_string(),LsItem { style: None, color: None, bg: None, description: "regular file".to_string(), order: 7 });
-        color_decs.insert("ln".to_string(),LsItem { style: None, color: None, bg: None, description: "symbolic link".to_string(), order: 8 });
-        color_decs.insert("mh".to_string(),LsItem { style: None, color: None, bg: None, description: "multi-hardlink".to_string(), order: 9 });
-        color_decs.insert("mi".to_string(),LsItem { style: None, color: None, bg: None, description: "missing file".to_string(), order: 10 });
-        color_decs.insert("no".to_string(),LsItem { style: None, color: None, bg: None, description: "normal non-filename text".to_string(), order: 11 });
-        color_decs.insert("or".to_string(),LsItem { style: None, color: None, bg: None, description: "orphan symlink".to_string(), order: 12 });
-        color_decs.insert("ow".to_string(),LsItem { style: None, color: None, bg: None, description: "other-writable directory".to_string(), order: 13 });
-        color_decs.insert("pi".to_string(),LsItem { style: None, color: None, bg: None, description: "named pipe, AKA FIFO".to_string(), order: 14 });
-        color_decs.insert("rs".to_string(),LsItem { style: None, color: None, bg: None, description: "reset to no color".to_string(), order: 1 });
-        color_decs.insert("sg".to_string(),LsItem { style: None, color: None, bg: None, description: "set-group-ID".to_string(), order: 16 });
-        color_decs.insert("so".to_string(),LsItem { style: None, color: None, bg: None, description: "socket".to_string(), order: 17 });
-        color_decs.insert("st".to_string(),LsItem { style: None, color: None, bg: None, description: "sticky directory".to_string(), order: 18 });
-        color_decs.insert("su".to_string(),LsItem { style: None, color: None, bg: None, description: "set-user-ID".to_string(), order: 19 });
-        color_decs.insert("tw".to_string(),LsItem { style: None, color: None, bg: None, description: "sticky and other-writable directory".to_string(), order: 20 });
-        Self(color_decs)
+        Self::new(&schema::LS_COLORS)
     }
 }
+/// what ls uses for the file types when LS_COLORS doesn't set them
+pub const LS_BUILTIN: &str = schema::LS_COLORS.builtin;
+
 impl Deref for ColorMap {
     type Target = HashMap<String, LsItem>;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.entries
     }
 }
 
 impl DerefMut for ColorMap {
    
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.entries
     }
 }
 
 impl ColorMap {
-    
+
+    /// every key of the schema without values
+    pub fn new(schema: &'static Schema) -> Self {
+        let entries = schema.keys.iter().enumerate().map(|(i, (key, description))| {
+            (key.to_string(), LsItem { style: None, color: None, bg: None, description: description.to_string(), order: i + 1, reference: None })
+        }).collect();
+        Self { entries, schema }
+    }
+
+    /// LS_COLORS map without the built-in entries, used for partial maps like layers
+    pub fn empty() -> Self {
+        Self::empty_for(&schema::LS_COLORS)
+    }
+
+    pub fn empty_for(schema: &'static Schema) -> Self {
+        Self { entries: HashMap::new(), schema }
+    }
+
     pub fn parse_env_string(&mut self, env_string: String) {
-        let color_split: Vec<_> = env_string.split(':').map(|s| s.to_string()).filter(|s| s.len() > 0).collect();
+        let color_split: Vec<_> = env_string.split(':').map(|s| s.to_string()).filter(|s| !s.is_empty()).collect();
 
             for item in &color_split {
                 //println!("{:?}", item);
-                let (key, values) = item.split_once('=').unwrap();
-                let (style_split, fg_split, bg_split) = parse_values(values);
-
-                let style: Option<Style>;
-                let fg: Option<Color>;
-                let bg: Option<Background>;
-
-                if let Some(style_str) = style_split {
-                    //Ansi color 
-                    if style_str == "38" {
-                        style = None;
-                    } else {
-                        style = Style::from_str(style_str).ok();
+                let Some((key, values)) = item.split_once('=') else {
+                    if self.schema.is_flag(item) {
+                        self.set_flag(item, true);
                     }
-                } else {
-                    style = None;
-                }
+                    continue;
+                };
+                let (style, fg, bg) = decode_values(values);
+                self.set_values(key, style, fg, bg);
+            }
+    }
 
-                if let Some(fg_str) = fg_split {
-                    fg = Color::from_str(fg_str).ok();
-                } else {
-                    fg = None;
-                }
+    /// sets the values of an entry, unknown keys are added as file extension (or style variable)
+    pub fn set_values(&mut self, key: &str, style: Option<Style>, color: Option<Color>, bg: Option<Background>) {
+        if let Some(ls_item) = self.get_mut(key) {
+            ls_item.style = style;
+            ls_item.color = color;
+            ls_item.bg = bg;
+            ls_item.reference = None;
+        } else {
+            let order = self.len() + 1;
+            let description = self.schema.description(key).map_or_else(|| default_description(key), str::to_string);
+            self.insert(key.to_owned(), LsItem { style, color, bg, description, order, reference: None});
+        }
+        self.copy_to_members(key);
+    }
 
-                if let Some(bg_str) = bg_split {
-                    bg = Background::from_str(bg_str).ok();
-                } else {
-                    bg = None;
-                }
+    /// gives the members of a shorthand its values and reference, like the program does when reading it
+    pub fn copy_to_members(&mut self, key: &str) {
+        let Some(item) = self.get(key).cloned() else {
+            return;
+        };
+        for member in self.schema.members(key) {
+            self.set_values(member, item.style.clone(), item.color.clone(), item.bg.clone());
+            self.entry(member.to_string()).and_modify(|m| m.reference = item.reference.clone());
+        }
+    }
+
+    /// flags have no values, they are on when the entry has a style
+    pub fn set_flag(&mut self, key: &str, on: bool) {
+        self.set_values(key, on.then_some(Style::None), None, None);
+    }
+
+    pub fn flag_is_on(&self, key: &str) -> bool {
+        self.get(key).is_some_and(|item| item.style.is_some())
+    }
+
+    /// links an entry to a style variable, the variable has to exist
+    pub fn set_reference(&mut self, key: &str, variable: &str) -> Result<(), String> {
+        if !is_variable(variable) || !self.contains_key(variable) {
+            return Err(format!("unknown style variable: {}", variable));
+        }
+        if is_variable(key) {
+            return Err("style variables can't reference other variables".to_string());
+        }
+        let item = self.get_mut(key).ok_or(format!("unknown entry: {}", key))?;
+        item.style = None;
+        item.color = None;
+        item.bg = None;
+        item.reference = Some(variable.to_string());
+        self.copy_to_members(key);
+        Ok(())
+    }
+
+    /// unlinks an entry from its style variable, keeping the current look
+    pub fn detach(&mut self, key: &str) {
+        let Some(item) = self.get(key) else {
+            return;
+        };
+        if item.reference.is_none() {
+            return;
+        }
+        let values = self.resolve(item);
+        let (style, color, bg) = (values.style.clone(), values.color.clone(), values.bg.clone());
+        self.set_values(key, style, color, bg);
+    }
+
+    /// the entry that provides the values for `item` (the referenced variable or the item itself)
+    pub fn resolve<'a>(&'a self, item: &'a LsItem) -> &'a LsItem {
+        item.reference.as_ref().and_then(|r| self.get(r)).unwrap_or(item)
+    }
+
+    /// copy of the map where references are replaced by the variable values,
+    /// the reference names are kept for display, the variables and eza keys only if `keep_variables`
+    pub fn flatten(&self, keep_variables: bool) -> ColorMap {
+        let mut flat = HashMap::new();
+        for (key, item) in self.iter() {
+            if (is_variable(key) || crate::eza::is_eza_key(key)) && !keep_variables {
+                continue;
+            }
+            let values = self.resolve(item);
+            let mut flat_item = item.clone();
+            flat_item.style = values.style.clone();
+            flat_item.color = values.color.clone();
+            flat_item.bg = values.bg.clone();
+            flat.insert(key.clone(), flat_item);
+        }
+        ColorMap { entries: flat, schema: self.schema }
+    }
+
+    /// keys of the entries referencing the variable
+    pub fn users_of(&self, variable: &str) -> Vec<&str> {
+        let mut users: Vec<(&str, usize)> = self.iter()
+            .filter(|(_, item)| item.reference.as_deref() == Some(variable))
+            .map(|(key, item)| (key.as_str(), item.order))
+            .collect();
+        users.sort_by_key(|(_, order)| *order);
+        users.into_iter().map(|(key, _)| key).collect()
+    }
+}
+
+/// style variables are entries named $name, they are never exported themselves
+pub fn is_variable(key: &str) -> bool {
+    key.starts_with('$')
+}
 
-                if let Some(ls_item) = self.get_mut(key) {
-                    ls_item.style = style;
-                    ls_item.color = fg;
-                    ls_item.bg = bg;
+pub fn default_description(key: &str) -> String {
+    if let Some(description) = crate::eza::description(key).or_else(|| crate::lf::description(key)) {
+        description
+    } else if is_variable(key) {
+        format!("style variable: {}", key)
+    } else {
+        format!("file extension: {}", key)
+    }
+}
+
+/// the decoded style, color and background of an entry
+pub type Values = (Option<Style>, Option<Color>, Option<Background>);
+
+/// decodes the value of an LS_COLORS entry (ex.: '01;34', '1;38;5;208') into style, color and background
+pub fn decode_values(values: &str) -> Values {
+    let codes: Vec<u8> = values.split(';').filter_map(|c| c.parse::<u8>().ok()).collect();
+    let (mut style, mut fg, mut bg) = (None, None, None);
+
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            n @ (0 | 1 | 4 | 5 | 7 | 8) => style = Style::from_str(&format!("{:02}", n)).ok(),
+            n @ (30..=37 | 90..=97) => fg = Color::from_str(&n.to_string()).ok(),
+            n @ (40..=47 | 100..=107) => bg = Background::from_str(&n.to_string()).ok(),
+            n @ (38 | 48) => {
+                //Ansi color 38;5;n or rgb color 38;2;r;g;b
+                let color = match codes.get(i + 1) {
+                    Some(5) if i + 2 < codes.len() => {
+                        i += 2;
+                        Some(Color::_Ansi(codes[i]))
+                    },
+                    Some(2) if i + 4 < codes.len() => {
+                        i += 4;
+                        Some(Color::_RGB((codes[i - 2], codes[i - 1], codes[i])))
+                    },
+                    _ => None,
+                };
+                if n == 38 {
+                    fg = color.or(fg);
                 } else {
-                    let order = self.len() + 1;
-                    self.insert(key.to_owned(), LsItem { style, color: fg, bg, description: format!("file extension: {}", key), order});
+                    bg = color.map(|c| Background::from_color(&c)).or(bg);
                 }
-            }
+            },
+            _ => (),
+        }
+        i += 1;
     }
+    (style, fg, bg)
 }
 //https://askubuntu.com/questions/466198/how-do-i-change-the-color-for-directories-with-ls-in-the-console
 //https://www.bigsoft.co.uk/blog/2008/04/11/configuring-ls_colors
 // https://askubuntu.com/questions/17299/what-do-the-different-colors-mean-in-ls
 
 //https://en.wikipedia.org/wiki/ANSI_escape_code#8-bit
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum Style {
     None,
     Bold,
@@ -123,19 +252,20 @@ impl FromStr for Style {
     }
 }
 
-impl ToString for Style {
-    fn to_string(&self) -> String {
-        match self {
-            Self::None => "00".into(),
-            Self::Bold => "01".into(),
-            Self::Underscore => "04".into(),
-            Self::Blink => "05".into(),
-            Self::Reverse => "07".into(),
-            Self::Concealed => "08".into(),
-        }
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::None => "00",
+            Self::Bold => "01",
+            Self::Underscore => "04",
+            Self::Blink => "05",
+            Self::Reverse => "07",
+            Self::Concealed => "08",
+        };
+        f.write_str(s)
     }
 }
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum Color {
     Black,
     Red,
@@ -153,7 +283,9 @@ pub enum Color {
     BrightMagenta,
     BrightCyan,
     BrightWhite,
+    #[serde(rename = "Ansi")]
     _Ansi(u8), //38;5
+    #[serde(rename = "RGB")]
     _RGB((u8, u8, u8)),
 }
 
@@ -181,30 +313,115 @@ impl FromStr for Color {
         }
     }
 }
-impl ToString for Color {
-    fn to_string(&self) -> String {
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Black => "30",
+            Self::Red => "31",
+            Self::Green => "32",
+            Self::Yellow => "33",
+            Self::Blue => "34",
+            Self::Magenta => "35",
+            Self::Cyan => "36",
+            Self::White => "37",
+            Self::BrightBlack => "90",
+            Self::BrightRed => "91",
+            Self::BrightGreen => "92",
+            Self::BrightYellow => "93",
+            Self::BrightBlue => "94",
+            Self::BrightMagenta => "95",
+            Self::BrightCyan => "96",
+            Self::BrightWhite => "97",
+            Self::_Ansi(n) => return write!(f, "38;5;{}", n),
+            Self::_RGB((r, g, b)) => return write!(f, "38;2;{};{};{}", r, g, b),
+        };
+        f.write_str(s)
+    }
+}
+
+/// names of the 16 basic colors in ansi order
+pub const COLOR_NAMES: [&str; 16] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    "bright-black", "bright-red", "bright-green", "bright-yellow", "bright-blue", "bright-magenta", "bright-cyan", "bright-white",
+];
+
+impl Color {
+    /// the basic color with the given ansi index (0 - 15), 256 color table entry otherwise
+    pub fn from_index(n: u8) -> Self {
+        match n {
+            0 => Self::Black,
+            1 => Self::Red,
+            2 => Self::Green,
+            3 => Self::Yellow,
+            4 => Self::Blue,
+            5 => Self::Magenta,
+            6 => Self::Cyan,
+            7 => Self::White,
+            8 => Self::BrightBlack,
+            9 => Self::BrightRed,
+            10 => Self::BrightGreen,
+            11 => Self::BrightYellow,
+            12 => Self::BrightBlue,
+            13 => Self::BrightMagenta,
+            14 => Self::BrightCyan,
+            15 => Self::BrightWhite,
+            n => Self::_Ansi(n),
+        }
+    }
+    /// ansi index (0 - 15) of the basic colors
+    pub fn index(&self) -> Option<u8> {
         match self {
-            Self::Black => "30".into(),
-            Self::Red => "31".into(),
-            Self::Green => "32".into(),
-            Self::Yellow => "33".into(),
-            Self::Blue => "34".into(),
-            Self::Magenta => "35".into(),
-            Self::Cyan => "36".into(),
-            Self::White => "37".into(),
-            Self::BrightBlack => "90".into(),
-            Self::BrightRed => "91".into(),
-            Self::BrightGreen => "92".into(),
-            Self::BrightYellow => "93".into(),
-            Self::BrightBlue => "94".into(),
-            Self::BrightMagenta => "95".into(),
-            Self::BrightCyan => "96".into(),
-            Self::BrightWhite => "97".into(),
-            _ => "".into(),
+            Self::_Ansi(_) | Self::_RGB(_) => None,
+            basic => (0..16).find(|i| Self::from_index(*i) == *basic),
         }
     }
-} 
-#[derive(Debug, PartialEq, PartialOrd)]
+    /// approximate rgb value of the color (xterm default palette)
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        match self {
+            Self::Black => (0, 0, 0),
+            Self::Red => (205, 0, 0),
+            Self::Green => (0, 205, 0),
+            Self::Yellow => (205, 205, 0),
+            Self::Blue => (0, 0, 238),
+            Self::Magenta => (205, 0, 205),
+            Self::Cyan => (0, 205, 205),
+            Self::White => (229, 229, 229),
+            Self::BrightBlack => (127, 127, 127),
+            Self::BrightRed => (255, 0, 0),
+            Self::BrightGreen => (0, 255, 0),
+            Self::BrightYellow => (255, 255, 0),
+            Self::BrightBlue => (92, 92, 255),
+            Self::BrightMagenta => (255, 0, 255),
+            Self::BrightCyan => (0, 255, 255),
+            Self::BrightWhite => (255, 255, 255),
+            Self::_Ansi(n) => ansi_256_rgb(*n),
+            Self::_RGB(rgb) => *rgb,
+        }
+    }
+}
+
+/// rgb value of an entry in the xterm 256 color table
+pub fn ansi_256_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => {
+            const BASIC: [Color; 16] = [
+                Color::Black, Color::Red, Color::Green, Color::Yellow, Color::Blue, Color::Magenta, Color::Cyan, Color::White,
+                Color::BrightBlack, Color::BrightRed, Color::BrightGreen, Color::BrightYellow, Color::BrightBlue, Color::BrightMagenta, Color::BrightCyan, Color::BrightWhite,
+            ];
+            BASIC[n as usize].rgb()
+        },
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = n - 16;
+            (level(i / 36), level((i / 6) % 6), level(i % 6))
+        },
+        _ => {
+            let v = 8 + (n - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum Background {
     Black,
     Red,
@@ -222,7 +439,9 @@ pub enum Background {
     BrightMagenta,
     BrightCyan,
     BrightWhite,
+    #[serde(rename = "Ansi")]
     _Ansi(u8),
+    #[serde(rename = "RGB")]
     _RGB((u8, u8, u8)),
 }
 
@@ -251,40 +470,77 @@ impl FromStr for Background {
         }
     }
 }
-impl ToString for Background {
-    fn to_string(&self) -> String {
+impl fmt::Display for Background {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Black => "40",
+            Self::Red => "41",
+            Self::Green => "42",
+            Self::Yellow => "43",
+            Self::Blue => "44",
+            Self::Magenta => "45",
+            Self::Cyan => "46",
+            Self::White => "47",
+            Self::BrightBlack => "100",
+            Self::BrightRed => "101",
+            Self::BrightGreen => "102",
+            Self::BrightYellow => "103",
+            Self::BrightBlue => "104",
+            Self::BrightMagenta => "105",
+            Self::BrightCyan => "106",
+            Self::BrightWhite => "107",
+            Self::_Ansi(n) => return write!(f, "48;5;{}", n),
+            Self::_RGB((r, g, b)) => return write!(f, "48;2;{};{};{}", r, g, b),
+        };
+        f.write_str(s)
+    }
+} 
+impl Background {
+    /// the background with the same color
+    pub fn from_color(color: &Color) -> Self {
+        match color {
+            Color::Black => Self::Black,
+            Color::Red => Self::Red,
+            Color::Green => Self::Green,
+            Color::Yellow => Self::Yellow,
+            Color::Blue => Self::Blue,
+            Color::Magenta => Self::Magenta,
+            Color::Cyan => Self::Cyan,
+            Color::White => Self::White,
+            Color::BrightBlack => Self::BrightBlack,
+            Color::BrightRed => Self::BrightRed,
+            Color::BrightGreen => Self::BrightGreen,
+            Color::BrightYellow => Self::BrightYellow,
+            Color::BrightBlue => Self::BrightBlue,
+            Color::BrightMagenta => Self::BrightMagenta,
+            Color::BrightCyan => Self::BrightCyan,
+            Color::BrightWhite => Self::BrightWhite,
+            Color::_Ansi(n) => Self::_Ansi(*n),
+            Color::_RGB(rgb) => Self::_RGB(*rgb),
+        }
+    }
+    /// the foreground with the same color
+    pub fn to_color(&self) -> Color {
         match self {
-            Self::Black => "40".into(),
-            Self::Red => "41".into(),
-            Self::Green => "42".into(),
-            Self::Yellow => "43".into(),
-            Self::Blue => "44".into(),
-            Self::Magenta => "45".into(),
-            Self::Cyan => "46".into(),
-            Self::White => "47".into(),
-            Self::BrightBlack => "100".into(),
-            Self::BrightRed => "101".into(),
-            Self::BrightGreen => "102".into(),
-            Self::BrightYellow => "103".into(),
-            Self::BrightBlue => "104".into(),
-            Self::BrightMagenta => "105".into(),
-            Self::BrightCyan => "106".into(),
-            Self::BrightWhite => "107".into(),
-            _ => "".into(),
+            Self::_Ansi(n) => Color::_Ansi(*n),
+            Self::_RGB(rgb) => Color::_RGB(*rgb),
+            basic => (0..16).map(Color::from_index).find(|c| Self::from_color(c) == *basic).unwrap(),
         }
     }
-} 
-#[derive(Debug, PartialEq, PartialOrd)]
+}
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct LsItem {
     pub style: Option<Style>,
     pub color: Option<Color>,
     pub bg: Option<Background>,
     pub description: String,
     pub order: usize,
+    /// name of a style variable ($name) providing the values of this entry
+    pub reference: Option<String>,
 }
 
-impl ToString for LsItem {
-    fn to_string(&self) -> String {
+impl fmt::Display for LsItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut values = vec![];
         if let Some(style) = &self.style {
             values.push(style.to_string())
@@ -296,29 +552,44 @@ impl ToString for LsItem {
             values.push(bg.to_string())
         }
 
-        values.join(";")
+        write!(f, "{}", values.join(";"))
     }
 }
 //https://en.wikipedia.org/wiki/ANSI_escape_code
 impl LsItem {
-    pub fn display_colors_values(&self) -> String {
+    /// same look (or same variable reference), description and order are ignored
+    pub fn same_values(&self, other: &LsItem) -> bool {
+        self.same_look(other) && self.reference == other.reference
+    }
+
+    /// style, color and background alike, no matter where they come from
+    pub fn same_look(&self, other: &LsItem) -> bool {
+        self.style == other.style && self.color == other.color && self.bg == other.bg
+    }
+
+    fn display_values(&self) -> (String, String, String) {
         let style =  if let Some(style) = &self.style { style.to_string() } else { "--".to_string() };
         let fg =  if let Some(fg) = &self.color { fg.to_string() } else { "--".to_string() };
         let bg =  if let Some(bg) = &self.bg { bg.to_string() } else { "--".to_string() };
+        (style, fg, bg)
+    }
+
+    /// width of the color and background columns for the items (at least 7), 256 and rgb values are longer
+    pub fn column_widths<'a>(items: impl IntoIterator<Item = &'a LsItem>) -> (usize, usize) {
+        items.into_iter().map(LsItem::display_values).fold((7, 7), |(fg_width, bg_width), (_, fg, bg)| (fg_width.max(fg.len()), bg_width.max(bg.len())))
+    }
 
-        format!("{};{:>7};{:>7}", style, fg, bg)
+    /// style, color and background codes in columns of the given widths (see `column_widths`)
+    pub fn display_colors_values(&self, (fg_width, bg_width): (usize, usize)) -> String {
+        let (style, fg, bg) = self.display_values();
+        format!("{};{:>fg_width$};{:>bg_width$}", style, fg, bg)
     }
+    /// the text in the colors of the entry, plain when the output has no colors
     pub fn color_helper(&self, s: &str) -> String {
-        let preffix = "\x1b[";
-        let suffix  = "\x1b[0m";
-        format!("{}{}m{}{}", preffix, self.make_color_id(), s, suffix)
+        crate::capability::paint(&self.make_color_id(), s)
     }
     pub fn _preview(&self) -> String {
-        //println!("\x1b[0;31mSO\x1b[0m")
-        let preffix = "\x1b[";
-        let suffix  = "\x1b[0m";
-        
-        format!("{}{}m{}{}", preffix, self.make_color_id(), self.description, suffix)
+        self.color_helper(&self.description)
     }
 
     pub fn make_color_id(&self) -> String {
@@ -337,23 +608,3 @@ impl LsItem {
 
     }
 }
-
-//TODO parse ansi value 38
-pub fn parse_values(s: &str) -> (Option<&str>, Option<&str>, Option<&str>) {
-    let split = s.splitn(3, ';');
-    let sv: Vec<&str> = split.collect();
-    let mut v = [None, None, None];
-    for item in sv {
-        
-        match item.chars().next() {
-            Some('0') => v[0] = Some(item),
-            Some('3') => v[1] = Some(item),
-            Some('4') => v[2] = Some(item),
-            None => (),
-            _ => (),
-        }
-    }
-    
-    
-    (v[0], v[1], v[2])
-}
\ No newline at end of file
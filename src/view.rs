@@ -0,0 +1,361 @@
+use std::{cmp::Ordering, collections::{BTreeMap, HashMap}, ops::Deref};
+
+use clap::{Args, ValueEnum};
+
+use crate::{capability, categories, describe, eza, is_variable, json, lf, ColorMap, LsItem};
+use crate::cvd::Deficiency;
+use crate::depth::{self, Depth};
+use crate::palette::{self, Palette, TermBackground};
+use crate::theme::ThemeInfo;
+
+#[derive(Args, Debug, Default)]
+pub struct ViewArgs {
+    /// only show entries whose key matches the glob (ex.: '*.t*')
+    pub pattern: Option<String>,
+    /// only show the given entry (can be repeated)
+    #[arg(long = "key")]
+    pub keys: Vec<String>,
+    /// only show file extensions (*.ext)
+    #[arg(long, conflicts_with_all = ["builtin", "eza"])]
+    pub ext: bool,
+    /// only show the eza keys (eza:ur, eza:da ..)
+    #[arg(long, conflicts_with = "builtin")]
+    pub eza: bool,
+    /// only show the members of an extension category
+    #[arg(long)]
+    pub category: Option<String>,
+    /// only show the built-in file type entries (di, ln, ex ..) without style variables
+    #[arg(long)]
+    pub builtin: bool,
+    /// only show the entry lf uses for PATH ('~/src/' with a trailing '/' for directories), LF_COLORS only
+    #[arg(long = "match", value_name = "PATH")]
+    pub path: Option<String>,
+    #[arg(long, value_enum, default_value_t = SortMode::Order)]
+    pub sort: SortMode,
+    #[arg(long = "group-by", value_enum, default_value_t = GroupBy::Color)]
+    pub group_by: GroupBy,
+    /// print the matching entries as json instead of the preview
+    #[arg(long)]
+    pub json: bool,
+    /// show the contrast ratio against the terminal background
+    #[arg(long)]
+    pub contrast: bool,
+    /// entries below this ratio are marked (WCAG: 4.5)
+    #[arg(long, default_value_t = 4.5, requires = "contrast")]
+    pub min_contrast: f64,
+    #[arg(long, value_enum, default_value_t = TermBackground::Both, requires = "contrast")]
+    pub background: TermBackground,
+    /// render the colors of the palette in truecolor (how they look in that terminal)
+    #[arg(long)]
+    pub rgb: bool,
+    /// render the colors as seen with a color vision deficiency (truecolor)
+    #[arg(long, value_enum)]
+    pub simulate: Option<Deficiency>,
+    /// render the colors a terminal with this depth shows and mark the ones that collapse
+    #[arg(long, value_enum)]
+    pub depth: Option<Depth>,
+    /// one sentence per entry instead of colors (for screen readers)
+    #[arg(long, conflicts_with = "json")]
+    pub describe: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortMode {
+    /// alphabetical by key
+    Key,
+    /// in declared order
+    #[default]
+    Order,
+    /// by the color string
+    Color,
+    /// by the hue of the foreground color
+    Hue,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum GroupBy {
+    /// one line per entry
+    None,
+    /// extensions with identical color strings share a line
+    #[default]
+    Color,
+    /// built-in entries, style variables and each extension category in separate sections
+    Category,
+}
+
+pub fn is_extension(key: &str) -> bool {
+    key.starts_with("*.")
+}
+
+/// minimal glob matching supporting '*' and '?'
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// hue in degrees of the foreground color, None for entries without color or gray tones
+pub fn hue(item: &LsItem) -> Option<f32> {
+    let (r, g, b) = item.color.as_ref()?.rgb();
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    if delta == 0.0 {
+        return None;
+    }
+    let h = if max == r {
+        60.0 * (((g - b) / delta) % 6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    Some(if h < 0.0 { h + 360.0 } else { h })
+}
+
+impl ViewArgs {
+    pub fn matches(&self, key: &str, categories: &BTreeMap<String, Vec<String>>) -> bool {
+        if let Some(category) = &self.category {
+            if !categories.get(category).is_some_and(|members| members.iter().any(|m| m == key)) {
+                return false;
+            }
+        }
+        if self.ext && !is_extension(key) {
+            return false;
+        }
+        if self.builtin && (is_extension(key) || is_variable(key) || eza::is_eza_key(key) || lf::is_path_key(key)) {
+            return false;
+        }
+        if self.eza && !eza::is_eza_key(key) {
+            return false;
+        }
+        if !self.keys.is_empty() && !self.keys.iter().any(|k| k == key) {
+            return false;
+        }
+        match &self.pattern {
+            Some(pattern) => glob_match(pattern, key),
+            None => true,
+        }
+    }
+
+    fn compare(&self, a: &(&str, &LsItem), b: &(&str, &LsItem)) -> Ordering {
+        match self.sort {
+            SortMode::Key => a.0.cmp(b.0),
+            SortMode::Order => a.1.order.cmp(&b.1.order),
+            SortMode::Color => a.1.make_color_id().cmp(&b.1.make_color_id()).then(a.1.order.cmp(&b.1.order)),
+            SortMode::Hue => {
+                // colored entries by hue, then gray tones, then entries without color
+                let rank = |item: &LsItem| match (hue(item), &item.color) {
+                    (Some(h), _) => (0, h),
+                    (None, Some(_)) => (1, 0.0),
+                    (None, None) => (2, 0.0),
+                };
+                let (ra, ha) = rank(a.1);
+                let (rb, hb) = rank(b.1);
+                ra.cmp(&rb).then(ha.total_cmp(&hb)).then(a.1.order.cmp(&b.1.order))
+            }
+        }
+    }
+}
+
+/// contrast ratios of the entry for `view --contrast`, empty otherwise
+fn contrast_note(item: &LsItem, args: &ViewArgs, palette: &Palette) -> String {
+    if !args.contrast {
+        return String::new();
+    }
+    let mut low = vec![];
+    let ratios: Vec<String> = args.background.variants().into_iter().filter_map(|background| {
+        let ratio = palette::entry_contrast(palette, item, background)?;
+        if ratio < args.min_contrast {
+            low.push(background.name());
+        }
+        Some(format!("{} {:.2}", background.name(), ratio))
+    }).collect();
+    match (ratios.is_empty(), low.is_empty()) {
+        (true, _) => "  contrast: terminal default".to_string(),
+        (false, true) => format!("  contrast: {}", ratios.join(", ")),
+        (false, false) => format!("  contrast: {}  ! low on {}", ratios.join(", "), low.join(" and ")),
+    }
+}
+
+pub fn print_preview_map(color_decs: &ColorMap, info: &ThemeInfo, palette: &Palette, args: &ViewArgs) {
+    let categories = categories::all(info);
+    let original = color_decs.flatten(true);
+    let flat = match args.depth {
+        Some(depth) => depth::downsample_map(&original, depth, palette),
+        None => original.clone(),
+    };
+    let resolved = match &args.path {
+        // the rules of lf, ls and tree match their patterns differently
+        Some(_) if !color_decs.schema.paths => {
+            println!("--match follows the rules of lf, it only applies to LF_COLORS ('variable LF_COLORS')");
+            return;
+        },
+        Some(path) => lf::resolve(&flat, path),
+        None => None,
+    };
+    let mut entries: Vec<(&str, &LsItem)> = flat.deref().iter()
+        .filter(|(key, _)| args.matches(key, &categories) && (args.path.is_none() || resolved == Some(key.as_str())))
+        .map(|(key, item)| (key.as_str(), item))
+        .collect();
+    if entries.is_empty() {
+        println!("no matching entries");
+        return;
+    }
+    entries.sort_by(|a, b| args.compare(a, b));
+
+    if args.json {
+        match json::to_json(&entries) {
+            Ok(json) => println!("{}", json),
+            Err(e) => println!("{}", e),
+        }
+        return;
+    }
+    if args.describe {
+        entries.iter().for_each(|(key, item)| println!("{}", describe::describe_entry(key, item, palette)));
+        return;
+    }
+
+    // downsampled look -> original looks -> keys
+    let mut collapsed: HashMap<String, BTreeMap<String, Vec<&str>>> = HashMap::new();
+    if args.depth.is_some() {
+        for (key, item) in &entries {
+            if item.color.is_some() || item.bg.is_some() {
+                collapsed.entry(item.to_string()).or_default().entry(original[*key].to_string()).or_default().push(key);
+            }
+        }
+        collapsed.retain(|_, originals| originals.len() > 1);
+    }
+
+    // downsampled to what the terminal shows unless --depth asks for a specific depth
+    let render_depth = capability::depth().filter(|d| args.depth.is_none() && *d != Depth::Truecolor);
+    let widths = LsItem::column_widths(entries.iter().map(|(_, item)| *item));
+    let preview = Preview { args, palette, collapsed, render_depth, widths };
+    match args.group_by {
+        GroupBy::None => entries.iter().for_each(|entry| preview.print_entry(entry)),
+        GroupBy::Color => preview.print_color_groups(&entries),
+        GroupBy::Category => {
+            let mut sections: BTreeMap<String, Vec<(&str, &LsItem)>> = BTreeMap::new();
+            for entry in entries {
+                let title = if is_variable(entry.0) {
+                    "style variables"
+                } else if eza::is_eza_key(entry.0) {
+                    "eza"
+                } else if lf::is_path_key(entry.0) {
+                    "lf paths"
+                } else if !is_extension(entry.0) {
+                    "built-in"
+                } else {
+                    categories::category_of(&categories, entry.0).unwrap_or("other extensions")
+                };
+                sections.entry(title.to_string()).or_default().push(entry);
+            }
+            // fixed sections around the categories (in alphabetical order)
+            let fixed = ["built-in", "style variables", "other extensions", "lf paths", "eza"];
+            let mut titles: Vec<&str> = fixed[..2].to_vec();
+            titles.extend(sections.keys().map(|t| t.as_str()).filter(|t| !fixed.contains(t)));
+            titles.extend(&fixed[2..]);
+            for title in titles {
+                if let Some(section) = sections.get(title) {
+                    println!("{} ({}):", title, section.len());
+                    section.iter().for_each(|entry| preview.print_entry(entry));
+                }
+            }
+        }
+    }
+}
+
+/// how the entries are rendered and annotated
+struct Preview<'a> {
+    args: &'a ViewArgs,
+    palette: &'a Palette,
+    collapsed: HashMap<String, BTreeMap<String, Vec<&'a str>>>,
+    render_depth: Option<Depth>,
+    /// color and background columns fit the longest value of the shown entries
+    widths: (usize, usize),
+}
+
+impl Preview<'_> {
+    fn paint(&self, item: &LsItem, text: &str) -> String {
+        if let Some(deficiency) = self.args.simulate {
+            self.palette.paint_with(item, text, |rgb| deficiency.simulate(rgb))
+        } else if self.args.rgb {
+            self.palette.paint(item, text)
+        } else if let Some(depth) = self.render_depth {
+            depth::downsample_item(item, depth, self.palette).color_helper(text)
+        } else {
+            item.color_helper(text)
+        }
+    }
+
+    /// contrast and the different looks that end up as this one with `--depth`
+    fn note(&self, item: &LsItem) -> String {
+        let mut note = contrast_note(item, self.args, self.palette);
+        if let Some(originals) = self.collapsed.get(&item.to_string()) {
+            let looks: Vec<String> = originals.iter().map(|(values, keys)| format!("{} ({})", values, keys.join(", "))).collect();
+            note.push_str(&format!("  ! {} looks collapse at {} colors: {}", originals.len(), self.args.depth.map_or("", |d| d.name()), looks.join(", ")));
+        }
+        note
+    }
+
+    fn print_entry(&self, (key, item): &(&str, &LsItem)) {
+        let note = self.note(item);
+        match &item.reference {
+            Some(variable) => println!("{} : [{} -> {}] {}{}", item.display_colors_values(self.widths), key, variable, self.paint(item, &item.description), note),
+            None => println!("{} : [{}] {}{}", item.display_colors_values(self.widths), key, self.paint(item, &item.description), note),
+        }
+    }
+
+    fn print_color_groups(&self, entries: &[(&str, &LsItem)]) {
+        let mut preview_map: HashMap<String, Vec<(&str, &LsItem)>> = HashMap::new();
+        let mut group_order: Vec<String> = vec![];
+
+        for &(key, item) in entries {
+            let mut k = item.to_string();
+            if !is_extension(key) {
+                k.push_str(" - ");
+                k.push_str(key);
+            } else {
+                k.push_str(" - ext");
+            }
+            if !preview_map.contains_key(&k) {
+                group_order.push(k.clone());
+            }
+            preview_map.entry(k).or_default().push((key, item));
+        }
+
+        for k in &group_order {
+            let item = &preview_map[k];
+            if item.len() > 1 {
+                let files_vec = item.iter().map(|e| e.0).collect::<Vec<_>>();
+                let mut chunks = files_vec.chunks(13);
+                println!("{} : {}{}", item[0].1.display_colors_values(self.widths), self.paint(item[0].1, chunks.next().unwrap().join(";").as_ref()), self.note(item[0].1));
+                for c in chunks {
+                    // lines up with the first line, after the values column
+                    let indent = item[0].1.display_colors_values(self.widths).len() + 3;
+                    println!("{}{}", " ".repeat(indent), self.paint(item[0].1, &c.join(";")));
+                }
+            } else {
+                self.print_entry(&item[0]);
+            }
+        }
+    }
+}
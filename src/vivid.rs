@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use serde_yaml::Value;
+
+use crate::eza::get_path;
+use crate::palette::parse_hex;
+use crate::{Background, Color, ColorMap, Style, Values};
+
+/// one list of the filetypes database: its path ('programming.source.rust') and the map keys
+#[derive(Debug)]
+struct FileClass {
+    path: Vec<String>,
+    keys: Vec<String>,
+}
+
+/// '$di' is the file type di, '.rs' the extension *.rs, anything else a file name ('Makefile' -> *Makefile)
+fn key_of(entry: &str) -> String {
+    match entry.strip_prefix('$') {
+        Some(file_type) => file_type.to_string(),
+        None => format!("*{}", entry),
+    }
+}
+
+/// the lists of the filetypes database in file order
+fn file_classes(node: &Value, path: &mut Vec<String>, classes: &mut Vec<FileClass>) -> Result<(), String> {
+    match node {
+        Value::Mapping(children) => {
+            for (name, child) in children {
+                let name = name.as_str().ok_or(format!("{}: category names must be strings", path.join(".")))?;
+                path.push(name.to_string());
+                file_classes(child, path, classes)?;
+                path.pop();
+            }
+        },
+        Value::Sequence(entries) => {
+            let keys = entries.iter().map(|e| e.as_str().map(key_of).ok_or(format!("{}: entries must be strings", path.join(".")))).collect::<Result<_, _>>()?;
+            classes.push(FileClass { path: path.clone(), keys });
+        },
+        Value::String(entry) => classes.push(FileClass { path: path.clone(), keys: vec![key_of(entry)] }),
+        _ => return Err(format!("{}: expected a list of file types", path.join("."))),
+    }
+    Ok(())
+}
+
+/// the style properties of a theme node, inherited by everything below it
+#[derive(Debug, Default, Clone)]
+struct ThemeStyle {
+    foreground: Option<Color>,
+    background: Option<Color>,
+    font_styles: Vec<String>,
+}
+
+fn theme_color(name: &str, colors: Option<&Value>) -> Result<Color, String> {
+    let hex = colors.and_then(|c| c.get(name)).and_then(Value::as_str).unwrap_or(name);
+    parse_hex(hex).map(Color::_RGB).ok_or(format!("unknown color: {}", name))
+}
+
+impl ThemeStyle {
+    /// the style with the properties of `node` on top
+    fn apply(&self, node: &Value, colors: Option<&Value>) -> Result<Self, String> {
+        let mut style = self.clone();
+        if let Some(name) = node.get("foreground").and_then(Value::as_str) {
+            style.foreground = Some(theme_color(name, colors)?);
+        }
+        if let Some(name) = node.get("background").and_then(Value::as_str) {
+            style.background = Some(theme_color(name, colors)?);
+        }
+        match node.get("font-style") {
+            Some(Value::String(font)) => style.font_styles = vec![font.clone()],
+            Some(Value::Sequence(fonts)) => style.font_styles = fonts.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+            _ => (),
+        }
+        Ok(style)
+    }
+
+    /// the values of the entry, the first font style LS_COLORS has is kept
+    fn values(&self, key: &str, warnings: &mut Vec<String>) -> Values {
+        let mut style = None;
+        for font in &self.font_styles {
+            let ls_style = match font.as_str() {
+                "regular" => Some(Style::None),
+                "bold" => Some(Style::Bold),
+                "underline" => Some(Style::Underscore),
+                "blink" => Some(Style::Blink),
+                "reverse" => Some(Style::Reverse),
+                "hidden" => Some(Style::Concealed),
+                _ => None,
+            };
+            match (ls_style, &style) {
+                (Some(s), None) => style = Some(s),
+                _ => warnings.push(format!("{}: the font style {} is dropped", key, font)),
+            }
+        }
+        (style, self.foreground.clone(), self.background.as_ref().map(Background::from_color))
+    }
+}
+
+/// the style of a file class: the properties of the theme nodes along its path, deeper ones win
+fn class_style(theme: &Value, path: &[String]) -> Result<Option<ThemeStyle>, String> {
+    let colors = theme.get("colors");
+    let mut style: Option<ThemeStyle> = None;
+    for depth in 1..=path.len() {
+        let Some(node) = get_path(theme, &path[..depth].join(".")) else {
+            break;
+        };
+        style = Some(style.unwrap_or_default().apply(node, colors).map_err(|e| format!("{}: {}", path[..depth].join("."), e))?);
+    }
+    Ok(style)
+}
+
+/// a vivid setup translated to the map
+#[derive(Debug)]
+pub struct VividImport {
+    pub map: ColorMap,
+    /// every category of the database ('programming', 'programming.source', 'programming.source.rust' ..)
+    pub categories: BTreeMap<String, Vec<String>>,
+    /// what LS_COLORS can't hold
+    pub warnings: Vec<String>,
+}
+
+/// the entries of a vivid theme applied to a filetypes database
+pub fn import_vivid(filetypes: &str, theme: &str) -> Result<VividImport, Box<dyn std::error::Error>> {
+    let filetypes: Value = serde_yaml::from_str(filetypes)?;
+    let theme: Value = serde_yaml::from_str(theme)?;
+    let mut classes = vec![];
+    file_classes(&filetypes, &mut vec![], &mut classes)?;
+
+    let mut map = ColorMap::default();
+    let mut categories: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut warnings = vec![];
+    let mut unstyled = vec![];
+    for class in &classes {
+        // 'core' holds the file types, they are not extensions
+        if class.path.first().is_some_and(|p| p != "core") {
+            for depth in 1..=class.path.len() {
+                categories.entry(class.path[..depth].join(".")).or_default().extend(class.keys.iter().cloned());
+            }
+        }
+        match class_style(&theme, &class.path)? {
+            Some(style) => {
+                let (style, fg, bg) = style.values(&class.path.join("."), &mut warnings);
+                for key in &class.keys {
+                    map.set_values(key, style.clone(), fg.clone(), bg.clone());
+                }
+            },
+            None => unstyled.push(class.path.join(".")),
+        }
+    }
+    if !unstyled.is_empty() {
+        warnings.push(format!("the theme has no style for {}, their entries are left out", unstyled.join(", ")));
+    }
+    Ok(VividImport { map, categories, warnings })
+}